@@ -1,7 +1,10 @@
-use config::{config::Config, control::WheelDeviceControl};
+use config::{
+    config::{Config, TORQUE_CURVE_SIZE},
+    control::WheelDeviceControl,
+};
 use hidapi::{HidApi, HidDevice};
 use std::slice::Iter;
-use force_feedback::reports::RacingWheelState;
+use force_feedback::{oscillation::MAX_CROSSINGS, reports::RacingWheelState};
 
 const USB_VID: u16 = 0xF055;
 const USB_PID: u16 = 0x5555;
@@ -24,7 +27,7 @@ fn send_config(device: &HidDevice, config: Config) -> Result<(), Error> {
 }
 
 fn read_config(device: &HidDevice) -> Result<Config, Error> {
-    let mut buf = [0; 63];
+    let mut buf = [0; Config::REPORT_SIZE];
     buf[0] = CONFIG_REPORT_ID;
 
     let bytes_read = device
@@ -79,6 +82,57 @@ fn set_option(mut args: Iter<String>) -> Result<(), Error> {
         "update_frequency_hz" => {
             config.update_frequency_hz = value.parse().or(Err(Error::ParseError))?
         }
+        "acceleration_smoothing" => {
+            config.acceleration_smoothing = value.parse().or(Err(Error::ParseError))?
+        }
+        "low_pass_cutoff_hz" => {
+            config.low_pass_cutoff_hz = value.parse().or(Err(Error::ParseError))?
+        }
+        "notch_frequency_hz" => {
+            config.notch_frequency_hz = value.parse().or(Err(Error::ParseError))?
+        }
+        "notch_q" => config.notch_q = value.parse().or(Err(Error::ParseError))?,
+        "slew_rate_limit" => config.slew_rate_limit = value.parse().or(Err(Error::ParseError))?,
+        "end_stop_stiffness" => {
+            config.end_stop_stiffness = value.parse().or(Err(Error::ParseError))?
+        }
+        "end_stop_damping" => {
+            config.end_stop_damping = value.parse().or(Err(Error::ParseError))?
+        }
+        "end_stop_start" => config.end_stop_start = value.parse().or(Err(Error::ParseError))?,
+        "end_stop_bump" => config.end_stop_bump = value.parse().or(Err(Error::ParseError))?,
+        "constant_gain" => config.constant_gain = value.parse().or(Err(Error::ParseError))?,
+        "ramp_gain" => config.ramp_gain = value.parse().or(Err(Error::ParseError))?,
+        "periodic_gain" => config.periodic_gain = value.parse().or(Err(Error::ParseError))?,
+        "condition_gain" => config.condition_gain = value.parse().or(Err(Error::ParseError))?,
+        "custom_gain" => config.custom_gain = value.parse().or(Err(Error::ParseError))?,
+        "reconstruction_latency_ms" => {
+            config.reconstruction_latency_ms = value.parse().or(Err(Error::ParseError))?
+        }
+        "idle_timeout_s" => config.idle_timeout_s = value.parse().or(Err(Error::ParseError))?,
+        "idle_fade_ms" => config.idle_fade_ms = value.parse().or(Err(Error::ParseError))?,
+        "idle_oscillation_damping" => {
+            config.idle_oscillation_damping = value.parse().or(Err(Error::ParseError))?
+        }
+        "oscillation_window_ms" => {
+            config.oscillation_window_ms = value.parse().or(Err(Error::ParseError))?
+        }
+        "oscillation_min_crossings" => {
+            config.oscillation_min_crossings = parse_min_crossings(value)?
+        }
+        "oscillation_min_velocity" => {
+            config.oscillation_min_velocity = value.parse().or(Err(Error::ParseError))?
+        }
+        "oscillation_damping" => {
+            config.oscillation_damping = value.parse().or(Err(Error::ParseError))?
+        }
+        "oscillation_gain_reduction" => {
+            config.oscillation_gain_reduction = value.parse().or(Err(Error::ParseError))?
+        }
+        "oscillation_ramp_ms" => {
+            config.oscillation_ramp_ms = value.parse().or(Err(Error::ParseError))?
+        }
+        "torque_curve" => set_torque_curve(&mut config, std::iter::once(value).chain(args))?,
         _ => return Err(Error::InvalidArgument),
     }
 
@@ -87,6 +141,34 @@ fn set_option(mut args: Iter<String>) -> Result<(), Error> {
     Ok(())
 }
 
+// The detector only remembers the last MAX_CROSSINGS crossings, more could never be reached
+fn parse_min_crossings(value: &str) -> Result<u8, Error> {
+    let min_crossings: u8 = value.parse().or(Err(Error::ParseError))?;
+    if min_crossings as usize > MAX_CROSSINGS {
+        return Err(Error::InvalidArgument);
+    }
+    Ok(min_crossings)
+}
+
+// The curve points are given for evenly spaced torques, from zero to full torque. A single point
+// disables the curve.
+fn set_torque_curve<'a>(
+    config: &mut Config,
+    values: impl Iterator<Item = &'a String>,
+) -> Result<(), Error> {
+    let mut points = 0;
+    for value in values {
+        if points == TORQUE_CURVE_SIZE {
+            return Err(Error::InvalidArgument);
+        }
+        config.torque_curve[points] = value.parse().or(Err(Error::ParseError))?;
+        points += 1;
+    }
+
+    config.torque_curve_points = points as u8;
+    Ok(())
+}
+
 fn send_control_command(mut args: Iter<String>) -> Result<(), Error> {
     let hid = HidApi::new().or(Err(Error::UsbHidError))?;
     let device = hid.open(USB_VID, USB_PID).or(Err(Error::DeviceError))?;
@@ -118,6 +200,25 @@ fn read_config_action() -> Result<(), Error> {
     Ok(())
 }
 
+fn read_torque_curve() -> Result<(), Error> {
+    let hid = HidApi::new().or(Err(Error::UsbHidError))?;
+    let device = hid.open(USB_VID, USB_PID).or(Err(Error::DeviceError))?;
+
+    let config = read_config(&device)?;
+    let points = usize::min(config.torque_curve_points as usize, TORQUE_CURVE_SIZE);
+    if points < 2 {
+        println!("No torque curve, expo {} is used", config.expo);
+        return Ok(());
+    }
+
+    println!("torque  output");
+    for (index, output) in config.torque_curve[..points].iter().enumerate() {
+        println!("{:<7.3} {:.3}", index as f32 / (points - 1) as f32, output);
+    }
+
+    Ok(())
+}
+
 fn print_help() -> Result<(), Error> {
     println!(
     r#"
@@ -130,6 +231,9 @@ fn print_help() -> Result<(), Error> {
         control CONTROL_COMMAND     Perform some control action, see CONTROL_COMMAND for the list
                                     of control commands.
         read_config                 Read the current configuration options.
+        read_torque_curve           Print the torque linearization curve.
+        read_state                  Print the steering, buttons, force and oscillation
+                                    suppression level reported by the device.
         help                        Display this help page.
 
     CONFIG_COMMAND:
@@ -150,6 +254,47 @@ fn print_help() -> Result<(), Error> {
         motor_deadband <md>         Set the motor deadband.
         motor_frequency_hz <mf>     Set the motor frequency.
         update_frequency_hz <uf>    Set the update frequency.
+        acceleration_smoothing <as> Set the acceleration smoothing.
+        low_pass_cutoff_hz <lc>     Set the output low-pass cutoff, 0 disables it.
+        notch_frequency_hz <nf>     Set the output notch frequency, 0 disables it.
+        notch_q <nq>                Set the output notch filter Q, higher is narrower.
+        slew_rate_limit <sr>        Set the largest torque change per second (full scale torque
+                                    per second), 0 disables the limit.
+        end_stop_stiffness <es>     Set the end stop stiffness.
+        end_stop_damping <ed>       Set the end stop damping.
+        end_stop_start <est>        Set where the end stop starts, as a fraction of the max
+                                    rotation (0.99 starts it just before the max rotation).
+        end_stop_bump <eb>          Set the force of the bump when the end stop is reached, 0
+                                    disables it.
+        constant_gain <cg>          Set the gain of constant force effects.
+        ramp_gain <rg>              Set the gain of ramp force effects.
+        periodic_gain <pg>          Set the gain of periodic effects.
+        condition_gain <cg>         Set the gain of condition effects.
+        custom_gain <cg>            Set the gain of custom force effects.
+        reconstruction_latency_ms <rl>
+                                    Set the longest time a constant or ramp force update from
+                                    the game is smoothed over, 0 disables it.
+        idle_timeout_s <it>         Only apply the config spring and damper after no effects
+                                    have been created or played for this many seconds, 0 always
+                                    applies them.
+        idle_fade_ms <if>           Set the time the idle spring and damper fade in and out.
+        idle_oscillation_damping <id>
+                                    Set the extra damping while idle and the wheel oscillates.
+        oscillation_window_ms <ow>  Set the time window the center crossings of a hands-off
+                                    oscillation are counted in.
+        oscillation_min_crossings <oc>
+                                    Set how many fast center crossings within the window are
+                                    an oscillation, up to 16. 0 disables the detection.
+        oscillation_min_velocity <ov>
+                                    Set the smallest steering velocity of a crossing.
+        oscillation_damping <od>    Set the extra damping while the wheel oscillates.
+        oscillation_gain_reduction <og>
+                                    Set how much the gain is reduced while the wheel oscillates,
+                                    from 0 to 1.
+        oscillation_ramp_ms <or>    Set the time the oscillation suppression ramps in and out.
+        torque_curve <o1> ... <on>  Set the motor output for up to 8 evenly spaced torques, from
+                                    zero to full torque. Replaces the expo, a single point
+                                    disables the curve.
 
     CONTROL_COMMAND:
         reboot                      Reboot the device.
@@ -176,11 +321,12 @@ fn read_state() -> Result<(), Error> {
             steering: f32_from_2_bytes(&buf[2..4]).unwrap(),
             throttle: f32_from_2_bytes(&buf[4..6]).unwrap(),
             ffb: f32_from_2_bytes(&buf[6..8]).unwrap(),
+            oscillation_suppression: f32_from_2_bytes(&buf[8..10]).unwrap(),
         };
         racing_wheel_state.buttons[0] = (buf[1] & 1) != 0;
         racing_wheel_state.buttons[1] = (buf[1] & 2) != 0;
 
-        println!("{} {} {} {}", -racing_wheel_state.steering, racing_wheel_state.buttons[0], racing_wheel_state.buttons[1], racing_wheel_state.ffb);
+        println!("{} {} {} {} {}", -racing_wheel_state.steering, racing_wheel_state.buttons[0], racing_wheel_state.buttons[1], racing_wheel_state.ffb, racing_wheel_state.oscillation_suppression);
     }
 }
 
@@ -197,6 +343,7 @@ fn main() {
         "config" => set_option(args[2..].iter()),
         "control" => send_control_command(args[2..].iter()),
         "read_config" => read_config_action(),
+        "read_torque_curve" => read_torque_curve(),
         "read_state" => read_state(),
         "help" => print_help(),
         "" => Err(Error::NotEnoughArguments),
@@ -1,4 +1,7 @@
-#[derive(Clone, Copy, Debug)]
+// Maximum number of points in the torque linearization curve
+pub const TORQUE_CURVE_SIZE: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(C)]
 pub struct Config {
     pub gain: f32,
@@ -18,10 +21,55 @@ pub struct Config {
     pub motor_deadband: f32,
     pub motor_frequency_hz: u16,
     pub update_frequency_hz: u16,
+    pub acceleration_smoothing: f32,
+    // Output filters, a frequency or rate of zero disables the filter
+    pub low_pass_cutoff_hz: f32,
+    pub notch_frequency_hz: f32,
+    pub notch_q: f32,
+    // Largest change of the torque per second, in full scale torque
+    pub slew_rate_limit: f32,
+    // Virtual end stop, the start is a fraction of max_rotation
+    pub end_stop_stiffness: f32,
+    pub end_stop_damping: f32,
+    pub end_stop_start: f32,
+    // Force of a short pulse when the end stop is reached, 0 disables it
+    pub end_stop_bump: f32,
+    // Motor output at evenly spaced torques from zero to full torque, replaces the expo. Only the
+    // first torque_curve_points points are used, with less than two the expo is used instead.
+    pub torque_curve: [f32; TORQUE_CURVE_SIZE],
+    pub torque_curve_points: u8,
+    // Gain of the game effects, by effect type
+    pub constant_gain: f32,
+    pub ramp_gain: f32,
+    pub periodic_gain: f32,
+    pub condition_gain: f32,
+    pub custom_gain: f32,
+    // Longest time to spread a constant or ramp force update over, 0 disables it
+    pub reconstruction_latency_ms: u16,
+    // The config spring and damper are only applied after no effects have been created or played
+    // for idle_timeout_s, and fade in and out over idle_fade_ms. A timeout of 0 always applies them.
+    pub idle_timeout_s: u16,
+    pub idle_fade_ms: u16,
+    // Extra damping while idle and the wheel oscillates
+    pub idle_oscillation_damping: f32,
+    // Hands-off oscillation detection, the wheel oscillates when it crosses the center faster
+    // than the min velocity at least min crossings times within the window. Zero min crossings
+    // disables the detection.
+    pub oscillation_window_ms: u16,
+    pub oscillation_min_crossings: u8,
+    pub oscillation_min_velocity: f32,
+    // Extra damping and gain reduction (0 to 1) ramped in over oscillation_ramp_ms while the
+    // wheel oscillates
+    pub oscillation_damping: f32,
+    pub oscillation_gain_reduction: f32,
+    pub oscillation_ramp_ms: u16,
 }
 
 impl Config {
-    pub fn into_bytes(&self, id: u8) -> [u8; 63] {
+    // Size of the config feature report, including the report id.
+    pub const REPORT_SIZE: usize = 179;
+
+    pub fn into_bytes(&self, id: u8) -> [u8; Self::REPORT_SIZE] {
         [
             id,
             f32::to_le_bytes(self.gain)[0],
@@ -86,6 +134,122 @@ impl Config {
             u16::to_le_bytes(self.motor_frequency_hz)[1],
             u16::to_le_bytes(self.update_frequency_hz)[0],
             u16::to_le_bytes(self.update_frequency_hz)[1],
+            f32::to_le_bytes(self.acceleration_smoothing)[0],
+            f32::to_le_bytes(self.acceleration_smoothing)[1],
+            f32::to_le_bytes(self.acceleration_smoothing)[2],
+            f32::to_le_bytes(self.acceleration_smoothing)[3],
+            f32::to_le_bytes(self.low_pass_cutoff_hz)[0],
+            f32::to_le_bytes(self.low_pass_cutoff_hz)[1],
+            f32::to_le_bytes(self.low_pass_cutoff_hz)[2],
+            f32::to_le_bytes(self.low_pass_cutoff_hz)[3],
+            f32::to_le_bytes(self.notch_frequency_hz)[0],
+            f32::to_le_bytes(self.notch_frequency_hz)[1],
+            f32::to_le_bytes(self.notch_frequency_hz)[2],
+            f32::to_le_bytes(self.notch_frequency_hz)[3],
+            f32::to_le_bytes(self.notch_q)[0],
+            f32::to_le_bytes(self.notch_q)[1],
+            f32::to_le_bytes(self.notch_q)[2],
+            f32::to_le_bytes(self.notch_q)[3],
+            f32::to_le_bytes(self.slew_rate_limit)[0],
+            f32::to_le_bytes(self.slew_rate_limit)[1],
+            f32::to_le_bytes(self.slew_rate_limit)[2],
+            f32::to_le_bytes(self.slew_rate_limit)[3],
+            f32::to_le_bytes(self.end_stop_stiffness)[0],
+            f32::to_le_bytes(self.end_stop_stiffness)[1],
+            f32::to_le_bytes(self.end_stop_stiffness)[2],
+            f32::to_le_bytes(self.end_stop_stiffness)[3],
+            f32::to_le_bytes(self.end_stop_damping)[0],
+            f32::to_le_bytes(self.end_stop_damping)[1],
+            f32::to_le_bytes(self.end_stop_damping)[2],
+            f32::to_le_bytes(self.end_stop_damping)[3],
+            f32::to_le_bytes(self.end_stop_start)[0],
+            f32::to_le_bytes(self.end_stop_start)[1],
+            f32::to_le_bytes(self.end_stop_start)[2],
+            f32::to_le_bytes(self.end_stop_start)[3],
+            f32::to_le_bytes(self.end_stop_bump)[0],
+            f32::to_le_bytes(self.end_stop_bump)[1],
+            f32::to_le_bytes(self.end_stop_bump)[2],
+            f32::to_le_bytes(self.end_stop_bump)[3],
+            f32::to_le_bytes(self.torque_curve[0])[0],
+            f32::to_le_bytes(self.torque_curve[0])[1],
+            f32::to_le_bytes(self.torque_curve[0])[2],
+            f32::to_le_bytes(self.torque_curve[0])[3],
+            f32::to_le_bytes(self.torque_curve[1])[0],
+            f32::to_le_bytes(self.torque_curve[1])[1],
+            f32::to_le_bytes(self.torque_curve[1])[2],
+            f32::to_le_bytes(self.torque_curve[1])[3],
+            f32::to_le_bytes(self.torque_curve[2])[0],
+            f32::to_le_bytes(self.torque_curve[2])[1],
+            f32::to_le_bytes(self.torque_curve[2])[2],
+            f32::to_le_bytes(self.torque_curve[2])[3],
+            f32::to_le_bytes(self.torque_curve[3])[0],
+            f32::to_le_bytes(self.torque_curve[3])[1],
+            f32::to_le_bytes(self.torque_curve[3])[2],
+            f32::to_le_bytes(self.torque_curve[3])[3],
+            f32::to_le_bytes(self.torque_curve[4])[0],
+            f32::to_le_bytes(self.torque_curve[4])[1],
+            f32::to_le_bytes(self.torque_curve[4])[2],
+            f32::to_le_bytes(self.torque_curve[4])[3],
+            f32::to_le_bytes(self.torque_curve[5])[0],
+            f32::to_le_bytes(self.torque_curve[5])[1],
+            f32::to_le_bytes(self.torque_curve[5])[2],
+            f32::to_le_bytes(self.torque_curve[5])[3],
+            f32::to_le_bytes(self.torque_curve[6])[0],
+            f32::to_le_bytes(self.torque_curve[6])[1],
+            f32::to_le_bytes(self.torque_curve[6])[2],
+            f32::to_le_bytes(self.torque_curve[6])[3],
+            f32::to_le_bytes(self.torque_curve[7])[0],
+            f32::to_le_bytes(self.torque_curve[7])[1],
+            f32::to_le_bytes(self.torque_curve[7])[2],
+            f32::to_le_bytes(self.torque_curve[7])[3],
+            self.torque_curve_points,
+            f32::to_le_bytes(self.constant_gain)[0],
+            f32::to_le_bytes(self.constant_gain)[1],
+            f32::to_le_bytes(self.constant_gain)[2],
+            f32::to_le_bytes(self.constant_gain)[3],
+            f32::to_le_bytes(self.ramp_gain)[0],
+            f32::to_le_bytes(self.ramp_gain)[1],
+            f32::to_le_bytes(self.ramp_gain)[2],
+            f32::to_le_bytes(self.ramp_gain)[3],
+            f32::to_le_bytes(self.periodic_gain)[0],
+            f32::to_le_bytes(self.periodic_gain)[1],
+            f32::to_le_bytes(self.periodic_gain)[2],
+            f32::to_le_bytes(self.periodic_gain)[3],
+            f32::to_le_bytes(self.condition_gain)[0],
+            f32::to_le_bytes(self.condition_gain)[1],
+            f32::to_le_bytes(self.condition_gain)[2],
+            f32::to_le_bytes(self.condition_gain)[3],
+            f32::to_le_bytes(self.custom_gain)[0],
+            f32::to_le_bytes(self.custom_gain)[1],
+            f32::to_le_bytes(self.custom_gain)[2],
+            f32::to_le_bytes(self.custom_gain)[3],
+            u16::to_le_bytes(self.reconstruction_latency_ms)[0],
+            u16::to_le_bytes(self.reconstruction_latency_ms)[1],
+            u16::to_le_bytes(self.idle_timeout_s)[0],
+            u16::to_le_bytes(self.idle_timeout_s)[1],
+            u16::to_le_bytes(self.idle_fade_ms)[0],
+            u16::to_le_bytes(self.idle_fade_ms)[1],
+            f32::to_le_bytes(self.idle_oscillation_damping)[0],
+            f32::to_le_bytes(self.idle_oscillation_damping)[1],
+            f32::to_le_bytes(self.idle_oscillation_damping)[2],
+            f32::to_le_bytes(self.idle_oscillation_damping)[3],
+            u16::to_le_bytes(self.oscillation_window_ms)[0],
+            u16::to_le_bytes(self.oscillation_window_ms)[1],
+            u8::to_le_bytes(self.oscillation_min_crossings)[0],
+            f32::to_le_bytes(self.oscillation_min_velocity)[0],
+            f32::to_le_bytes(self.oscillation_min_velocity)[1],
+            f32::to_le_bytes(self.oscillation_min_velocity)[2],
+            f32::to_le_bytes(self.oscillation_min_velocity)[3],
+            f32::to_le_bytes(self.oscillation_damping)[0],
+            f32::to_le_bytes(self.oscillation_damping)[1],
+            f32::to_le_bytes(self.oscillation_damping)[2],
+            f32::to_le_bytes(self.oscillation_damping)[3],
+            f32::to_le_bytes(self.oscillation_gain_reduction)[0],
+            f32::to_le_bytes(self.oscillation_gain_reduction)[1],
+            f32::to_le_bytes(self.oscillation_gain_reduction)[2],
+            f32::to_le_bytes(self.oscillation_gain_reduction)[3],
+            u16::to_le_bytes(self.oscillation_ramp_ms)[0],
+            u16::to_le_bytes(self.oscillation_ramp_ms)[1],
         ]
     }
 
@@ -178,6 +342,233 @@ impl Config {
             ]),
             motor_frequency_hz: u16::from_le_bytes([*bytes.get(58)?, *bytes.get(59)?]),
             update_frequency_hz: u16::from_le_bytes([*bytes.get(60)?, *bytes.get(61)?]),
+            acceleration_smoothing: f32::from_le_bytes([
+                *bytes.get(62)?,
+                *bytes.get(63)?,
+                *bytes.get(64)?,
+                *bytes.get(65)?,
+            ]),
+            low_pass_cutoff_hz: f32::from_le_bytes([
+                *bytes.get(66)?,
+                *bytes.get(67)?,
+                *bytes.get(68)?,
+                *bytes.get(69)?,
+            ]),
+            notch_frequency_hz: f32::from_le_bytes([
+                *bytes.get(70)?,
+                *bytes.get(71)?,
+                *bytes.get(72)?,
+                *bytes.get(73)?,
+            ]),
+            notch_q: f32::from_le_bytes([
+                *bytes.get(74)?,
+                *bytes.get(75)?,
+                *bytes.get(76)?,
+                *bytes.get(77)?,
+            ]),
+            slew_rate_limit: f32::from_le_bytes([
+                *bytes.get(78)?,
+                *bytes.get(79)?,
+                *bytes.get(80)?,
+                *bytes.get(81)?,
+            ]),
+            end_stop_stiffness: f32::from_le_bytes([
+                *bytes.get(82)?,
+                *bytes.get(83)?,
+                *bytes.get(84)?,
+                *bytes.get(85)?,
+            ]),
+            end_stop_damping: f32::from_le_bytes([
+                *bytes.get(86)?,
+                *bytes.get(87)?,
+                *bytes.get(88)?,
+                *bytes.get(89)?,
+            ]),
+            end_stop_start: f32::from_le_bytes([
+                *bytes.get(90)?,
+                *bytes.get(91)?,
+                *bytes.get(92)?,
+                *bytes.get(93)?,
+            ]),
+            end_stop_bump: f32::from_le_bytes([
+                *bytes.get(94)?,
+                *bytes.get(95)?,
+                *bytes.get(96)?,
+                *bytes.get(97)?,
+            ]),
+            torque_curve: [
+                f32::from_le_bytes([
+                    *bytes.get(98)?,
+                    *bytes.get(99)?,
+                    *bytes.get(100)?,
+                    *bytes.get(101)?,
+                ]),
+                f32::from_le_bytes([
+                    *bytes.get(102)?,
+                    *bytes.get(103)?,
+                    *bytes.get(104)?,
+                    *bytes.get(105)?,
+                ]),
+                f32::from_le_bytes([
+                    *bytes.get(106)?,
+                    *bytes.get(107)?,
+                    *bytes.get(108)?,
+                    *bytes.get(109)?,
+                ]),
+                f32::from_le_bytes([
+                    *bytes.get(110)?,
+                    *bytes.get(111)?,
+                    *bytes.get(112)?,
+                    *bytes.get(113)?,
+                ]),
+                f32::from_le_bytes([
+                    *bytes.get(114)?,
+                    *bytes.get(115)?,
+                    *bytes.get(116)?,
+                    *bytes.get(117)?,
+                ]),
+                f32::from_le_bytes([
+                    *bytes.get(118)?,
+                    *bytes.get(119)?,
+                    *bytes.get(120)?,
+                    *bytes.get(121)?,
+                ]),
+                f32::from_le_bytes([
+                    *bytes.get(122)?,
+                    *bytes.get(123)?,
+                    *bytes.get(124)?,
+                    *bytes.get(125)?,
+                ]),
+                f32::from_le_bytes([
+                    *bytes.get(126)?,
+                    *bytes.get(127)?,
+                    *bytes.get(128)?,
+                    *bytes.get(129)?,
+                ]),
+            ],
+            torque_curve_points: *bytes.get(130)?,
+            constant_gain: f32::from_le_bytes([
+                *bytes.get(131)?,
+                *bytes.get(132)?,
+                *bytes.get(133)?,
+                *bytes.get(134)?,
+            ]),
+            ramp_gain: f32::from_le_bytes([
+                *bytes.get(135)?,
+                *bytes.get(136)?,
+                *bytes.get(137)?,
+                *bytes.get(138)?,
+            ]),
+            periodic_gain: f32::from_le_bytes([
+                *bytes.get(139)?,
+                *bytes.get(140)?,
+                *bytes.get(141)?,
+                *bytes.get(142)?,
+            ]),
+            condition_gain: f32::from_le_bytes([
+                *bytes.get(143)?,
+                *bytes.get(144)?,
+                *bytes.get(145)?,
+                *bytes.get(146)?,
+            ]),
+            custom_gain: f32::from_le_bytes([
+                *bytes.get(147)?,
+                *bytes.get(148)?,
+                *bytes.get(149)?,
+                *bytes.get(150)?,
+            ]),
+            reconstruction_latency_ms: u16::from_le_bytes([*bytes.get(151)?, *bytes.get(152)?]),
+            idle_timeout_s: u16::from_le_bytes([*bytes.get(153)?, *bytes.get(154)?]),
+            idle_fade_ms: u16::from_le_bytes([*bytes.get(155)?, *bytes.get(156)?]),
+            idle_oscillation_damping: f32::from_le_bytes([
+                *bytes.get(157)?,
+                *bytes.get(158)?,
+                *bytes.get(159)?,
+                *bytes.get(160)?,
+            ]),
+            oscillation_window_ms: u16::from_le_bytes([*bytes.get(161)?, *bytes.get(162)?]),
+            oscillation_min_crossings: u8::from_le_bytes([*bytes.get(163)?]),
+            oscillation_min_velocity: f32::from_le_bytes([
+                *bytes.get(164)?,
+                *bytes.get(165)?,
+                *bytes.get(166)?,
+                *bytes.get(167)?,
+            ]),
+            oscillation_damping: f32::from_le_bytes([
+                *bytes.get(168)?,
+                *bytes.get(169)?,
+                *bytes.get(170)?,
+                *bytes.get(171)?,
+            ]),
+            oscillation_gain_reduction: f32::from_le_bytes([
+                *bytes.get(172)?,
+                *bytes.get(173)?,
+                *bytes.get(174)?,
+                *bytes.get(175)?,
+            ]),
+            oscillation_ramp_ms: u16::from_le_bytes([*bytes.get(176)?, *bytes.get(177)?]),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_survives_round_trip() {
+        // Every field has a different value, so that swapped or overlapping fields are noticed
+        let config = Config {
+            gain: 0.9,
+            expo: 1.5,
+            derivative_smoothing: 0.25,
+            max_rotation: 900,
+            spring_gain: 0.1,
+            spring_coefficient: 0.2,
+            spring_saturation: 0.3,
+            spring_deadband: 0.01,
+            damper_gain: 0.4,
+            damper_coefficient: 0.5,
+            damper_saturation: 0.6,
+            damper_deadband: 0.02,
+            motor_min: 0.05,
+            motor_max: 0.95,
+            motor_deadband: 0.03,
+            motor_frequency_hz: 20_000,
+            update_frequency_hz: 500,
+            acceleration_smoothing: 0.75,
+            low_pass_cutoff_hz: 120.0,
+            notch_frequency_hz: 50.0,
+            notch_q: 0.7,
+            slew_rate_limit: 40.0,
+            end_stop_stiffness: 8.0,
+            end_stop_damping: 2.0,
+            end_stop_start: 0.97,
+            end_stop_bump: 0.35,
+            torque_curve: [0.0, 0.11, 0.22, 0.33, 0.44, 0.55, 0.66, 0.77],
+            torque_curve_points: 7,
+            constant_gain: 0.81,
+            ramp_gain: 0.82,
+            periodic_gain: 0.83,
+            condition_gain: 0.84,
+            custom_gain: 0.85,
+            reconstruction_latency_ms: 60,
+            idle_timeout_s: 30,
+            idle_fade_ms: 1_500,
+            idle_oscillation_damping: 0.45,
+            oscillation_window_ms: 800,
+            oscillation_min_crossings: 4,
+            oscillation_min_velocity: 1.25,
+            oscillation_damping: 0.65,
+            oscillation_gain_reduction: 0.15,
+            oscillation_ramp_ms: 250,
+        };
+
+        let bytes = config.into_bytes(4);
+        assert_eq!(bytes[0], 4);
+        assert_eq!(Config::from_bytes(&bytes[1..]), Some(config));
+
+        // A truncated report is rejected
+        assert_eq!(Config::from_bytes(&bytes[1..Config::REPORT_SIZE - 1]), None);
+    }
+}
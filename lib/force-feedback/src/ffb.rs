@@ -1,42 +1,64 @@
 use crate::{
-    effect::{Effect, EffectParameter},
+    effect::Effect,
+    renderer::{CustomForceEffect, TypedEffect},
     reports::{
-        EffectType, SetCondition, SetConstantForce,
-        SetEffect, SetEnvelope, SetPeriodic, SetRampForce,
+        EffectType, SetCondition, SetConstantForce, SetCustomForce, SetEffect, SetEnvelope,
+        SetPeriodic, SetRampForce,
     },
 };
 
+// Steering velocity (in normalized steering units per second) at which friction reaches its full
+// force.
+pub(crate) const FRICTION_TRANSITION_VELOCITY: f32 = 0.05;
+
+// Size of one custom force sample in the custom data buffer. A sample has the same layout as the
+// Download Force Sample report, one signed byte for steering followed by one for throttle.
+pub const CUSTOM_FORCE_SAMPLE_SIZE: usize = 2;
+
+// The direction instances are signed bytes, the descriptor declares -120..=120 for them. In polar
+// mode that is -18000..=18000 hundredths of a degree, in cartesian mode the components of the
+// direction vector.
+pub(crate) const DIRECTION_LOGICAL_MAX: i32 = 120;
+
+// Effects that are not complete have no force, see `TypedEffect` for the validation.
 pub fn calculate_force_feedback(
     effect: &Effect,
     time: u32,
     position: f32,
     velocity: f32,
     acceleration: f32,
+    custom_data: &[u8],
 ) -> f32 {
-    use EffectParameter::*;
+    let effect = match TypedEffect::try_from(effect) {
+        Ok(effect) => effect,
+        Err(_) => return 0.0,
+    };
 
-    if let Some(duration) = effect.effect_report.map(|e| e.duration).flatten() {
+    if let Some(duration) = effect.effect_report().duration {
         if time > duration as u32 {
             return 0.0;
         }
     }
 
-    match (effect.effect_report, effect.parameter_1, effect.parameter_2) {
-        (Some(e), Some(ConstantForce(p1)), None) => constant_ffb(&e, &p1, None, time),
-        (Some(e), Some(ConstantForce(p1)), Some(Envelope(p2))) => constant_ffb(&e, &p1, Some(&p2), time),
-        (Some(e), Some(RampForce(p1)), None) => ramp_ffb(&e, &p1, None, time),
-        (Some(e), Some(RampForce(p1)), Some(Envelope(p2))) => ramp_ffb(&e, &p1, Some(&p2), time),
-        (Some(_), Some(CustomForce(_p)), None) => 0.0,
-        (Some(e), Some(Periodic(p1)), None) => periodic_ffb(&e, &p1, None, time),
-        (Some(e), Some(Periodic(p1)), Some(Envelope(p2))) => periodic_ffb(&e, &p1, Some(&p2), time),
-        (Some(e), Some(Condition(p1)), _) => match e.effect_type {
-            EffectType::Spring => condition_ffb(&e, &p1, position),
-            EffectType::Damper => condition_ffb(&e, &p1, velocity),
-            EffectType::Inertia => condition_ffb(&e, &p1, acceleration),
-            EffectType::Friction => 0.0,
+    match effect {
+        TypedEffect::ConstantForce(e) => {
+            constant_ffb(&e.effect, &e.constant_force, e.envelope.as_ref(), time)
+        }
+        TypedEffect::RampForce(e) => ramp_ffb(&e.effect, &e.ramp_force, e.envelope.as_ref(), time),
+        TypedEffect::Periodic(e) => periodic_ffb(&e.effect, &e.periodic, e.envelope.as_ref(), time),
+        TypedEffect::CustomForce(CustomForceEffect {
+            effect,
+            custom_force: Some(custom_force),
+            envelope,
+        }) => custom_ffb(&effect, &custom_force, envelope.as_ref(), custom_data, time),
+        TypedEffect::CustomForce(_) => 0.0,
+        TypedEffect::Condition(e) => match e.effect.effect_type {
+            EffectType::Spring => condition_ffb(&e.effect, &e.condition, position),
+            EffectType::Damper => condition_ffb(&e.effect, &e.condition, velocity),
+            EffectType::Inertia => condition_ffb(&e.effect, &e.condition, acceleration),
+            EffectType::Friction => friction_ffb(&e.effect, &e.condition, velocity),
             _ => 0.0,
         },
-        _ => 0.0,
     }
 }
 
@@ -66,6 +88,52 @@ fn calculate_envelope(envelope: Option<&SetEnvelope>, time: u32, duration: Optio
     }
 }
 
+// Fraction of a full turn given by a direction instance.
+fn direction_turns(direction: u8) -> f32 {
+    let turns = direction as i8 as f32 / (2 * DIRECTION_LOGICAL_MAX) as f32;
+    if turns < 0.0 {
+        turns + 1.0
+    } else {
+        turns
+    }
+}
+
+// Cartesian direction instances are the components of the direction vector, the first one along
+// the steering axis.
+pub(crate) fn cartesian_direction(effect: &SetEffect) -> (i32, i32) {
+    (
+        effect.direction_instance_1 as i8 as i32,
+        effect.direction_instance_2 as i8 as i32,
+    )
+}
+
+// Magnitude of a cartesian direction vector as a Q16.16 number. A vector without magnitude has
+// no direction, the effect is then applied along the steering axis.
+pub(crate) fn cartesian_magnitude(x: i32, y: i32) -> u64 {
+    (((x * x + y * y) as u64) << 32).isqrt()
+}
+
+// Projects the effect direction onto the steering axis. Condition effects have their own
+// parameter block per axis and do not use this.
+pub(crate) fn direction_scale(effect: &SetEffect) -> f32 {
+    if effect.direction_enable {
+        // Polar direction, 0 is north and 90 degrees is east (positive steering).
+        sine_fn(direction_turns(effect.direction_instance_1))
+    } else if effect.axis_x_enable && effect.axis_y_enable {
+        // Cartesian direction, the steering component relative to the magnitude of the vector.
+        // The magnitude is a Q16.16 integer square root, as core has no floating point one.
+        let (x, y) = cartesian_direction(effect);
+        match cartesian_magnitude(x, y) {
+            0 => 1.0,
+            magnitude => x as f32 / (magnitude as f32 / 65_536.0),
+        }
+    } else if effect.axis_y_enable {
+        0.0
+    } else {
+        1.0
+    }
+}
+
 fn condition_force(metric: f32, condition: &SetCondition) -> f32 {
     let force = if metric < condition.cp_offset - condition.dead_band {
         let velocity_delta = metric - (condition.cp_offset - condition.dead_band);
@@ -84,7 +152,29 @@ fn condition_force(metric: f32, condition: &SetCondition) -> f32 {
     )
 }
 
-fn constant_ffb(
+fn friction_force(velocity: f32, condition: &SetCondition) -> f32 {
+    // The friction force has a constant magnitude opposing the movement, but is ramped in over
+    // FRICTION_TRANSITION_VELOCITY to avoid chattering when the wheel is (almost) standing still.
+    let force = if velocity < condition.cp_offset - condition.dead_band {
+        let velocity_delta = velocity - (condition.cp_offset - condition.dead_band);
+        let slip = f32::max(velocity_delta / FRICTION_TRANSITION_VELOCITY, -1.0);
+        condition.negative_coefficient * slip
+    } else if velocity > condition.cp_offset + condition.dead_band {
+        let velocity_delta = velocity - (condition.cp_offset + condition.dead_band);
+        let slip = f32::min(velocity_delta / FRICTION_TRANSITION_VELOCITY, 1.0);
+        condition.positive_coefficient * slip
+    } else {
+        0.0
+    };
+
+    f32::clamp(
+        force,
+        -condition.negative_saturation,
+        condition.positive_saturation,
+    )
+}
+
+pub(crate) fn constant_ffb(
     effect: &SetEffect,
     constant_force: &SetConstantForce,
     envelope: Option<&SetEnvelope>,
@@ -92,10 +182,10 @@ fn constant_ffb(
 ) -> f32 {
     let force = constant_force.magnitude;
     let envelope = calculate_envelope(envelope, time, effect.duration);
-    force * envelope * effect.gain
+    force * envelope * effect.gain * direction_scale(effect)
 }
 
-fn ramp_ffb(
+pub(crate) fn ramp_ffb(
     effect: &SetEffect,
     ramp_force: &SetRampForce,
     envelope: Option<&SetEnvelope>,
@@ -106,48 +196,101 @@ fn ramp_ffb(
             + (ramp_force.ramp_end - ramp_force.ramp_start) * (time as f32 / duration as f32);
 
         let envelope = calculate_envelope(envelope, time, effect.duration);
-        force * envelope * effect.gain
+        force * envelope * effect.gain * direction_scale(effect)
     } else {
         0.0
     }
 }
 
-fn condition_ffb(
+pub(crate) fn custom_ffb(
     effect: &SetEffect,
-    condition_1: &SetCondition,
-    metric: f32,
+    custom_force: &SetCustomForce,
+    envelope: Option<&SetEnvelope>,
+    custom_data: &[u8],
+    time: u32,
 ) -> f32 {
+    let sample_count = custom_force.sample_count as u32;
+    let sample_period = match effect.sample_period {
+        Some(sample_period) => sample_period as u32,
+        None => return 0.0,
+    };
+
+    if sample_count == 0 {
+        return 0.0;
+    }
+
+    // The samples are repeated until the effect duration has elapsed.
+    let sample_time = time % (sample_count * sample_period);
+    let sample_index = sample_time / sample_period;
+    let fraction = (sample_time % sample_period) as f32 / sample_period as f32;
+
+    let sample = |index: u32| {
+        let address = custom_force.custom_force_data_offset as usize
+            + (index % sample_count) as usize * CUSTOM_FORCE_SAMPLE_SIZE;
+        custom_data
+            .get(address)
+            .map(|s| *s as i8 as f32 / i8::MAX as f32)
+            .unwrap_or(0.0)
+    };
+
+    let force_start = sample(sample_index);
+    let force_end = sample(sample_index + 1);
+    let force = force_start + (force_end - force_start) * fraction;
+
+    let envelope = calculate_envelope(envelope, time, effect.duration);
+    force * envelope * effect.gain * direction_scale(effect)
+}
+
+pub(crate) fn condition_ffb(effect: &SetEffect, condition_1: &SetCondition, metric: f32) -> f32 {
     let force = condition_force(metric, condition_1);
     force * effect.gain
 }
 
-fn periodic_ffb(
+pub(crate) fn friction_ffb(effect: &SetEffect, condition_1: &SetCondition, velocity: f32) -> f32 {
+    let force = friction_force(velocity, condition_1);
+    force * effect.gain
+}
+
+pub(crate) fn periodic_ffb(
     effect: &SetEffect,
     periodic: &SetPeriodic,
     envelope: Option<&SetEnvelope>,
     time: u32,
 ) -> f32 {
-    let f = match effect.effect_type{
+    let f = waveform(effect.effect_type);
+    let force_norm = if periodic.period > 0 {
+        let effect_time = time + ((periodic.phase as u64 * periodic.period as u64) / 36_000) as u32;
+        f((effect_time % periodic.period) as f32 / periodic.period as f32)
+    } else {
+        0.0
+    };
+
+    // The envelope only shapes the magnitude, the offset is applied as is.
+    let envelope = calculate_envelope(envelope, time, effect.duration);
+    let force = periodic.offset + periodic.magnitude * envelope * force_norm;
+
+    force * effect.gain * direction_scale(effect)
+}
+
+// Waveform of a periodic effect type, as a function of the time within a period (0 to 1)
+pub(crate) fn waveform(effect_type: EffectType) -> fn(f32) -> f32 {
+    match effect_type {
         EffectType::Square => square_fn,
         EffectType::Sine => sine_fn,
         EffectType::Triangle => triangle_fn,
         EffectType::SawtoothUp => sawtooth_up_fn,
         EffectType::SawtoothDown => sawtooth_down_fn,
         _ => |_| 0.0,
-    };
-    let effect_time = time + ((periodic.phase as u64 * periodic.period as u64) / 36_000) as u32;
-
-    let force_norm = f((effect_time % periodic.period) as f32 / periodic.period as f32);
-    let force = periodic.magnitude * force_norm;
-
-    let envelope = calculate_envelope(envelope, time, effect.duration);
-
-    force * envelope * effect.gain
+    }
 }
 
 fn square_fn(time: f32) -> f32 {
     let t = time - (time as i64) as f32;
-    if t >= 0.5 { 1.0 } else { -1.0 }
+    if t >= 0.5 {
+        1.0
+    } else {
+        -1.0
+    }
 }
 
 fn sine_fn(time: f32) -> f32 {
@@ -171,7 +314,13 @@ fn sine_fn(time: f32) -> f32 {
 }
 
 fn triangle_fn(time: f32) -> f32 {
-    2.0 * if time < 0.5 { time } else { 1.0 - time }
+    if time < 0.25 {
+        4.0 * time
+    } else if time < 0.75 {
+        2.0 - 4.0 * time
+    } else {
+        4.0 * time - 4.0
+    }
 }
 
 fn sawtooth_up_fn(time: f32) -> f32 {
@@ -181,3 +330,361 @@ fn sawtooth_up_fn(time: f32) -> f32 {
 fn sawtooth_down_fn(time: f32) -> f32 {
     -sawtooth_up_fn(time)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::EffectParameter;
+    use core::f32::consts::FRAC_1_SQRT_2;
+
+    fn friction_effect(
+        cp_offset: f32,
+        coefficient: f32,
+        saturation: f32,
+        dead_band: f32,
+    ) -> Effect {
+        let condition = SetCondition {
+            cp_offset,
+            positive_coefficient: coefficient,
+            negative_coefficient: coefficient,
+            positive_saturation: saturation,
+            negative_saturation: saturation,
+            dead_band,
+            ..Default::default()
+        };
+
+        Effect {
+            effect_report: Some(SetEffect {
+                effect_type: EffectType::Friction,
+                gain: 1.0,
+                ..Default::default()
+            }),
+            parameter_1: Some(EffectParameter::Condition(condition)),
+            parameter_2: Some(EffectParameter::Condition(condition)),
+        }
+    }
+
+    fn friction(effect: &Effect, velocity: f32) -> f32 {
+        calculate_force_feedback(effect, 0, 0.0, velocity, 0.0, &[])
+    }
+
+    fn custom_effect(sample_period: u16, offset: u16, sample_count: u16) -> Effect {
+        Effect {
+            effect_report: Some(SetEffect {
+                effect_type: EffectType::CustomForceData,
+                sample_period: Some(sample_period),
+                gain: 1.0,
+                ..Default::default()
+            }),
+            parameter_1: Some(EffectParameter::CustomForce(SetCustomForce {
+                effect_block_index: 1,
+                custom_force_data_offset: offset,
+                sample_count,
+            })),
+            parameter_2: None,
+        }
+    }
+
+    fn custom(effect: &Effect, time: u32, custom_data: &[u8]) -> f32 {
+        calculate_force_feedback(effect, time, 0.0, 0.0, 0.0, custom_data)
+    }
+
+    #[test]
+    fn friction_follows_velocity_sign() {
+        let effect = friction_effect(0.0, 0.5, 1.0, 0.0);
+
+        assert_eq!(friction(&effect, 1.0), 0.5);
+        assert_eq!(friction(&effect, -1.0), -0.5);
+        assert_eq!(friction(&effect, 0.0), 0.0);
+    }
+
+    #[test]
+    fn friction_magnitude_is_independent_of_speed() {
+        let effect = friction_effect(0.0, 0.5, 1.0, 0.0);
+
+        assert_eq!(friction(&effect, 0.5), friction(&effect, 5.0));
+    }
+
+    #[test]
+    fn friction_is_smooth_around_zero_velocity() {
+        let effect = friction_effect(0.0, 1.0, 1.0, 0.0);
+        let half_transition = FRICTION_TRANSITION_VELOCITY / 2.0;
+
+        assert!((friction(&effect, half_transition) - 0.5).abs() < 1e-6);
+        assert!((friction(&effect, -half_transition) + 0.5).abs() < 1e-6);
+        assert!(friction(&effect, 1e-4).abs() < 0.01);
+    }
+
+    #[test]
+    fn friction_is_clamped_to_saturation() {
+        let effect = friction_effect(0.0, 1.0, 0.3, 0.0);
+
+        assert_eq!(friction(&effect, 1.0), 0.3);
+        assert_eq!(friction(&effect, -1.0), -0.3);
+    }
+
+    #[test]
+    fn friction_is_zero_inside_dead_band() {
+        let effect = friction_effect(0.0, 1.0, 1.0, 0.2);
+
+        assert_eq!(friction(&effect, 0.1), 0.0);
+        assert_eq!(friction(&effect, -0.2), 0.0);
+        assert!(friction(&effect, 0.3) > 0.0);
+        assert!(friction(&effect, -0.3) < 0.0);
+    }
+
+    #[test]
+    fn friction_dead_band_is_centered_on_cp_offset() {
+        let effect = friction_effect(0.5, 1.0, 1.0, 0.1);
+
+        assert_eq!(friction(&effect, 0.5), 0.0);
+        assert_eq!(friction(&effect, 0.0), -1.0);
+        assert_eq!(friction(&effect, 1.0), 1.0);
+    }
+
+    #[test]
+    fn friction_is_scaled_by_effect_gain() {
+        let mut effect = friction_effect(0.0, 1.0, 1.0, 0.0);
+        effect.effect_report.as_mut().unwrap().gain = 0.25;
+
+        assert_eq!(friction(&effect, 1.0), 0.25);
+    }
+
+    #[test]
+    fn custom_force_interpolates_between_samples() {
+        let effect = custom_effect(10, 0, 2);
+        let data = [0, 0, 127, 0];
+
+        assert_eq!(custom(&effect, 0, &data), 0.0);
+        assert_eq!(custom(&effect, 5, &data), 0.5);
+        assert_eq!(custom(&effect, 10, &data), 1.0);
+    }
+
+    #[test]
+    fn custom_force_repeats_samples() {
+        let effect = custom_effect(10, 2, 2);
+        let data = [0, 0, 127, 0, (-127_i8) as u8, 0];
+
+        assert_eq!(custom(&effect, 0, &data), 1.0);
+        assert_eq!(custom(&effect, 10, &data), -1.0);
+        assert_eq!(custom(&effect, 20, &data), 1.0);
+        assert_eq!(custom(&effect, 15, &data), 0.0);
+    }
+
+    #[test]
+    fn custom_force_without_sample_period_is_zero() {
+        let mut effect = custom_effect(10, 0, 1);
+        effect.effect_report.as_mut().unwrap().sample_period = None;
+
+        assert_eq!(custom(&effect, 0, &[127, 0]), 0.0);
+    }
+
+    fn periodic_effect(effect_type: EffectType, magnitude: f32, offset: f32, phase: u16) -> Effect {
+        Effect {
+            effect_report: Some(SetEffect {
+                effect_type,
+                gain: 1.0,
+                ..Default::default()
+            }),
+            parameter_1: Some(EffectParameter::Periodic(SetPeriodic {
+                effect_block_index: 1,
+                magnitude,
+                offset,
+                phase,
+                period: 1000,
+            })),
+            parameter_2: None,
+        }
+    }
+
+    fn periodic(effect: &Effect, time: u32) -> f32 {
+        calculate_force_feedback(effect, time, 0.0, 0.0, 0.0, &[])
+    }
+
+    fn assert_waveform(effect_type: EffectType, golden: [f32; 8]) {
+        let effect = periodic_effect(effect_type, 1.0, 0.0, 0);
+        for (i, expected) in golden.into_iter().enumerate() {
+            let force = periodic(&effect, i as u32 * 125);
+            assert!(
+                (force - expected).abs() < 1e-3,
+                "sample {}: expected {}, got {}",
+                i,
+                expected,
+                force
+            );
+        }
+    }
+
+    #[test]
+    fn square_golden_values() {
+        assert_waveform(
+            EffectType::Square,
+            [-1.0, -1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn sine_golden_values() {
+        const SQRT_HALF: f32 = FRAC_1_SQRT_2;
+        assert_waveform(
+            EffectType::Sine,
+            [
+                0.0, SQRT_HALF, 1.0, SQRT_HALF, 0.0, -SQRT_HALF, -1.0, -SQRT_HALF,
+            ],
+        );
+    }
+
+    #[test]
+    fn triangle_golden_values() {
+        assert_waveform(
+            EffectType::Triangle,
+            [0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -0.5],
+        );
+    }
+
+    #[test]
+    fn sawtooth_up_golden_values() {
+        assert_waveform(
+            EffectType::SawtoothUp,
+            [0.0, 0.25, 0.5, 0.75, -1.0, -0.75, -0.5, -0.25],
+        );
+    }
+
+    #[test]
+    fn sawtooth_down_golden_values() {
+        assert_waveform(
+            EffectType::SawtoothDown,
+            [0.0, -0.25, -0.5, -0.75, 1.0, 0.75, 0.5, 0.25],
+        );
+    }
+
+    #[test]
+    fn periodic_phase_shifts_waveform() {
+        let effect = periodic_effect(EffectType::Sine, 1.0, 0.0, 9_000);
+
+        assert!((periodic(&effect, 0) - 1.0).abs() < 1e-3);
+        assert!((periodic(&effect, 250) - 0.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn periodic_offset_is_added_to_waveform() {
+        let effect = periodic_effect(EffectType::Sine, 0.5, 0.25, 0);
+
+        assert!((periodic(&effect, 0) - 0.25).abs() < 1e-3);
+        assert!((periodic(&effect, 250) - 0.75).abs() < 1e-3);
+        assert!((periodic(&effect, 750) + 0.25).abs() < 1e-3);
+    }
+
+    #[test]
+    fn periodic_envelope_only_scales_magnitude() {
+        let mut effect = periodic_effect(EffectType::Square, 0.5, 0.25, 0);
+        effect.parameter_2 = Some(EffectParameter::Envelope(SetEnvelope {
+            effect_block_index: 1,
+            attack_level: 0.0,
+            fade_level: 1.0,
+            attack_time: 1000,
+            fade_time: 0,
+        }));
+
+        assert_eq!(periodic(&effect, 0), 0.25);
+        assert!((periodic(&effect, 500) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn periodic_with_zero_period_is_offset() {
+        let mut effect = periodic_effect(EffectType::Sine, 1.0, 0.25, 0);
+        if let Some(EffectParameter::Periodic(periodic)) = effect.parameter_1.as_mut() {
+            periodic.period = 0;
+        }
+
+        assert_eq!(periodic(&effect, 100), 0.25);
+    }
+
+    fn directional_effect(
+        direction_enable: bool,
+        axes: (bool, bool),
+        direction: (i8, i8),
+    ) -> Effect {
+        Effect {
+            effect_report: Some(SetEffect {
+                effect_type: EffectType::ConstantForce,
+                gain: 1.0,
+                axis_x_enable: axes.0,
+                axis_y_enable: axes.1,
+                direction_enable,
+                direction_instance_1: direction.0 as u8,
+                direction_instance_2: direction.1 as u8,
+                ..Default::default()
+            }),
+            parameter_1: Some(EffectParameter::ConstantForce(SetConstantForce {
+                effect_block_index: 1,
+                magnitude: 1.0,
+            })),
+            parameter_2: None,
+        }
+    }
+
+    fn directional(direction_enable: bool, axes: (bool, bool), direction: i8) -> f32 {
+        cartesian(direction_enable, axes, (direction, 0))
+    }
+
+    fn cartesian(direction_enable: bool, axes: (bool, bool), direction: (i8, i8)) -> f32 {
+        let effect = directional_effect(direction_enable, axes, direction);
+        calculate_force_feedback(&effect, 0, 0.0, 0.0, 0.0, &[])
+    }
+
+    #[test]
+    fn polar_direction_is_projected_onto_steering() {
+        // 0, 45, 90, 180, -180 and -90 degrees
+        assert!(directional(true, (true, true), 0).abs() < 1e-2);
+        assert!((directional(true, (true, true), 30) - FRAC_1_SQRT_2).abs() < 1e-2);
+        assert!((directional(true, (true, true), 60) - 1.0).abs() < 1e-2);
+        assert!(directional(true, (true, true), 120).abs() < 1e-2);
+        assert!(directional(true, (true, true), -120).abs() < 1e-2);
+        assert!((directional(true, (true, true), -60) + 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn opposite_polar_directions_push_opposite_ways() {
+        let east = directional(true, (false, false), 60);
+        let west = directional(true, (false, false), -60);
+
+        assert!(east > 0.99);
+        assert!((east + west).abs() < 1e-2);
+    }
+
+    #[test]
+    fn cartesian_direction_is_projected_onto_steering() {
+        let force = |x: i8, y: i8| cartesian(false, (true, true), (x, y));
+
+        assert!((force(100, 0) - 1.0).abs() < 1e-4);
+        assert!(force(0, 100).abs() < 1e-4);
+        assert!((force(-100, 0) + 1.0).abs() < 1e-4);
+        assert!((force(100, 100) - FRAC_1_SQRT_2).abs() < 1e-4);
+        assert!((force(-30, 40) + 0.6).abs() < 1e-4);
+        assert!((force(3, -4) - 0.6).abs() < 1e-4);
+
+        // Only the direction matters, not the length of the vector
+        assert!((force(-120, 120) - force(-1, 1)).abs() < 1e-4);
+
+        // Without a direction the force is along the steering axis
+        assert_eq!(force(0, 0), 1.0);
+    }
+
+    #[test]
+    fn axis_enable_without_direction() {
+        assert_eq!(directional(false, (true, false), 0), 1.0);
+        assert_eq!(directional(false, (false, true), 0), 0.0);
+        assert_eq!(directional(false, (false, false), -120), 1.0);
+    }
+
+    #[test]
+    fn condition_effects_ignore_direction() {
+        let mut effect = friction_effect(0.0, 1.0, 1.0, 0.0);
+        let effect_report = effect.effect_report.as_mut().unwrap();
+        effect_report.direction_enable = true;
+        effect_report.direction_instance_1 = 192;
+
+        assert_eq!(friction(&effect, 1.0), 1.0);
+    }
+}
@@ -0,0 +1,563 @@
+// Fixed point implementation of the force feedback calculations, for targets without a floating
+// point unit. It has the same interface as `ffb::calculate_force_feedback`, and follows it as
+// closely as possible. Values are Q16.16 fixed point numbers, so 1.0 is represented by `ONE`.
+// The effect functions return fixed point forces, which are only converted to floating point
+// once the forces have been added up.
+use crate::{
+    effect::Effect,
+    ffb::{
+        cartesian_direction, cartesian_magnitude, CUSTOM_FORCE_SAMPLE_SIZE, DIRECTION_LOGICAL_MAX,
+    },
+    renderer::{CustomForceEffect, TypedEffect},
+    reports::{
+        EffectType, SetCondition, SetConstantForce, SetCustomForce, SetEffect, SetEnvelope,
+        SetPeriodic, SetRampForce,
+    },
+};
+
+pub(crate) type Fixed = i32;
+
+pub(crate) const FRACTION_BITS: u32 = 16;
+pub(crate) const ONE: Fixed = 1 << FRACTION_BITS;
+const HALF: Fixed = ONE / 2;
+const QUARTER: Fixed = ONE / 4;
+
+// Same as in the floating point implementation: 0.05 normalized steering units per second
+pub(crate) const FRICTION_TRANSITION_VELOCITY: Fixed = ONE / 20;
+
+pub fn calculate_force_feedback(
+    effect: &Effect,
+    time: u32,
+    position: f32,
+    velocity: f32,
+    acceleration: f32,
+    custom_data: &[u8],
+) -> f32 {
+    let effect = match TypedEffect::try_from(effect) {
+        Ok(effect) => effect,
+        Err(_) => return 0.0,
+    };
+
+    if let Some(duration) = effect.effect_report().duration {
+        if time > duration as u32 {
+            return 0.0;
+        }
+    }
+
+    let (position, velocity, acceleration) = (
+        to_fixed(position),
+        to_fixed(velocity),
+        to_fixed(acceleration),
+    );
+    let force = match effect {
+        TypedEffect::ConstantForce(e) => {
+            constant_ffb(&e.effect, &e.constant_force, e.envelope.as_ref(), time)
+        }
+        TypedEffect::RampForce(e) => ramp_ffb(&e.effect, &e.ramp_force, e.envelope.as_ref(), time),
+        TypedEffect::Periodic(e) => periodic_ffb(&e.effect, &e.periodic, e.envelope.as_ref(), time),
+        TypedEffect::CustomForce(CustomForceEffect {
+            effect,
+            custom_force: Some(custom_force),
+            envelope,
+        }) => custom_ffb(&effect, &custom_force, envelope.as_ref(), custom_data, time),
+        TypedEffect::CustomForce(_) => 0,
+        TypedEffect::Condition(e) => match e.effect.effect_type {
+            EffectType::Spring => condition_ffb(&e.effect, &e.condition, position),
+            EffectType::Damper => condition_ffb(&e.effect, &e.condition, velocity),
+            EffectType::Inertia => condition_ffb(&e.effect, &e.condition, acceleration),
+            EffectType::Friction => friction_ffb(&e.effect, &e.condition, velocity),
+            _ => 0,
+        },
+    };
+    to_f32(force)
+}
+
+pub(crate) fn to_fixed(value: f32) -> Fixed {
+    (value * ONE as f32) as Fixed
+}
+
+pub(crate) fn to_f32(value: Fixed) -> f32 {
+    value as f32 / ONE as f32
+}
+
+// Products and quotients saturate instead of wrapping around
+pub(crate) fn saturate(value: i64) -> Fixed {
+    value.clamp(Fixed::MIN as i64, Fixed::MAX as i64) as Fixed
+}
+
+pub(crate) fn mul(a: Fixed, b: Fixed) -> Fixed {
+    saturate((a as i64 * b as i64) >> FRACTION_BITS)
+}
+
+fn div(a: Fixed, b: Fixed) -> Fixed {
+    saturate(((a as i64) << FRACTION_BITS) / b as i64)
+}
+
+// numerator / denominator as a fixed point number, the denominator must not be zero
+fn ratio(numerator: u32, denominator: u32) -> Fixed {
+    (((numerator as u64) << FRACTION_BITS) / denominator as u64) as Fixed
+}
+
+fn calculate_envelope(envelope: Option<&SetEnvelope>, time: u32, duration: Option<u16>) -> Fixed {
+    if let Some(envelope) = envelope {
+        let mut result = ONE;
+        if time < envelope.attack_time {
+            let attack_level = to_fixed(envelope.attack_level);
+            let fade_force =
+                attack_level + mul(ONE - attack_level, ratio(time, envelope.attack_time));
+            result = Fixed::min(result, fade_force);
+        }
+        if let Some(duration) = duration {
+            let duration = duration as u32;
+
+            if time <= duration && time + envelope.fade_time > duration {
+                let fade_level = to_fixed(envelope.fade_level);
+                let fade_force =
+                    fade_level + mul(ONE - fade_level, ratio(duration - time, envelope.fade_time));
+
+                result = Fixed::min(result, fade_force);
+            }
+        }
+
+        result
+    } else {
+        ONE
+    }
+}
+
+// Fraction of a full turn given by a direction instance.
+fn direction_turns(direction: u8) -> Fixed {
+    let turns = direction as i8 as Fixed * ONE / (2 * DIRECTION_LOGICAL_MAX);
+    if turns < 0 {
+        turns + ONE
+    } else {
+        turns
+    }
+}
+
+// Projects the effect direction onto the steering axis. Condition effects have their own
+// parameter block per axis and do not use this.
+pub(crate) fn direction_scale(effect: &SetEffect) -> Fixed {
+    if effect.direction_enable {
+        // Polar direction, 0 is north and 90 degrees is east (positive steering).
+        sine_fn(direction_turns(effect.direction_instance_1))
+    } else if effect.axis_x_enable && effect.axis_y_enable {
+        // Cartesian direction, the steering component relative to the magnitude of the vector
+        let (x, y) = cartesian_direction(effect);
+        match cartesian_magnitude(x, y) {
+            0 => ONE,
+            magnitude => (((x as i64) << (2 * FRACTION_BITS)) / magnitude as i64) as Fixed,
+        }
+    } else if effect.axis_y_enable {
+        0
+    } else {
+        ONE
+    }
+}
+
+fn condition_force(metric: Fixed, condition: &SetCondition) -> Fixed {
+    let cp_offset = to_fixed(condition.cp_offset);
+    let dead_band = to_fixed(condition.dead_band);
+
+    let force = if metric < cp_offset - dead_band {
+        let velocity_delta = metric - (cp_offset - dead_band);
+        mul(to_fixed(condition.negative_coefficient), velocity_delta)
+    } else if metric > cp_offset + dead_band {
+        let velocity_delta = metric - (cp_offset + dead_band);
+        mul(to_fixed(condition.positive_coefficient), velocity_delta)
+    } else {
+        0
+    };
+
+    Fixed::clamp(
+        force,
+        -to_fixed(condition.negative_saturation),
+        to_fixed(condition.positive_saturation),
+    )
+}
+
+fn friction_force(velocity: Fixed, condition: &SetCondition) -> Fixed {
+    // The friction force has a constant magnitude opposing the movement, but is ramped in over
+    // FRICTION_TRANSITION_VELOCITY to avoid chattering when the wheel is (almost) standing still.
+    let cp_offset = to_fixed(condition.cp_offset);
+    let dead_band = to_fixed(condition.dead_band);
+
+    let force = if velocity < cp_offset - dead_band {
+        let velocity_delta = velocity - (cp_offset - dead_band);
+        let slip = Fixed::max(div(velocity_delta, FRICTION_TRANSITION_VELOCITY), -ONE);
+        mul(to_fixed(condition.negative_coefficient), slip)
+    } else if velocity > cp_offset + dead_band {
+        let velocity_delta = velocity - (cp_offset + dead_band);
+        let slip = Fixed::min(div(velocity_delta, FRICTION_TRANSITION_VELOCITY), ONE);
+        mul(to_fixed(condition.positive_coefficient), slip)
+    } else {
+        0
+    };
+
+    Fixed::clamp(
+        force,
+        -to_fixed(condition.negative_saturation),
+        to_fixed(condition.positive_saturation),
+    )
+}
+
+pub(crate) fn constant_ffb(
+    effect: &SetEffect,
+    constant_force: &SetConstantForce,
+    envelope: Option<&SetEnvelope>,
+    time: u32,
+) -> Fixed {
+    let force = to_fixed(constant_force.magnitude);
+    let envelope = calculate_envelope(envelope, time, effect.duration);
+    mul(
+        mul(mul(force, envelope), to_fixed(effect.gain)),
+        direction_scale(effect),
+    )
+}
+
+pub(crate) fn ramp_ffb(
+    effect: &SetEffect,
+    ramp_force: &SetRampForce,
+    envelope: Option<&SetEnvelope>,
+    time: u32,
+) -> Fixed {
+    if let Some(duration) = effect.duration {
+        let ramp_start = to_fixed(ramp_force.ramp_start);
+        let ramp_end = to_fixed(ramp_force.ramp_end);
+        let force = ramp_start + mul(ramp_end - ramp_start, ratio(time, duration as u32));
+
+        let envelope = calculate_envelope(envelope, time, effect.duration);
+        mul(
+            mul(mul(force, envelope), to_fixed(effect.gain)),
+            direction_scale(effect),
+        )
+    } else {
+        0
+    }
+}
+
+pub(crate) fn custom_ffb(
+    effect: &SetEffect,
+    custom_force: &SetCustomForce,
+    envelope: Option<&SetEnvelope>,
+    custom_data: &[u8],
+    time: u32,
+) -> Fixed {
+    let sample_count = custom_force.sample_count as u32;
+    let sample_period = match effect.sample_period {
+        Some(sample_period) => sample_period as u32,
+        None => return 0,
+    };
+
+    if sample_count == 0 {
+        return 0;
+    }
+
+    // The samples are repeated until the effect duration has elapsed.
+    let sample_time = time % (sample_count * sample_period);
+    let sample_index = sample_time / sample_period;
+    let fraction = ratio(sample_time % sample_period, sample_period);
+
+    let sample = |index: u32| {
+        let address = custom_force.custom_force_data_offset as usize
+            + (index % sample_count) as usize * CUSTOM_FORCE_SAMPLE_SIZE;
+        custom_data
+            .get(address)
+            .map(|s| *s as i8 as Fixed * ONE / i8::MAX as Fixed)
+            .unwrap_or(0)
+    };
+
+    let force_start = sample(sample_index);
+    let force_end = sample(sample_index + 1);
+    let force = force_start + mul(force_end - force_start, fraction);
+
+    let envelope = calculate_envelope(envelope, time, effect.duration);
+    mul(
+        mul(mul(force, envelope), to_fixed(effect.gain)),
+        direction_scale(effect),
+    )
+}
+
+pub(crate) fn condition_ffb(
+    effect: &SetEffect,
+    condition_1: &SetCondition,
+    metric: Fixed,
+) -> Fixed {
+    let force = condition_force(metric, condition_1);
+    mul(force, to_fixed(effect.gain))
+}
+
+pub(crate) fn friction_ffb(
+    effect: &SetEffect,
+    condition_1: &SetCondition,
+    velocity: Fixed,
+) -> Fixed {
+    let force = friction_force(velocity, condition_1);
+    mul(force, to_fixed(effect.gain))
+}
+
+pub(crate) fn periodic_ffb(
+    effect: &SetEffect,
+    periodic: &SetPeriodic,
+    envelope: Option<&SetEnvelope>,
+    time: u32,
+) -> Fixed {
+    let f = waveform(effect.effect_type);
+    let force_norm = if periodic.period > 0 {
+        let effect_time = time + ((periodic.phase as u64 * periodic.period as u64) / 36_000) as u32;
+        f(ratio(effect_time % periodic.period, periodic.period))
+    } else {
+        0
+    };
+
+    // The envelope only shapes the magnitude, the offset is applied as is.
+    let envelope = calculate_envelope(envelope, time, effect.duration);
+    let magnitude = to_fixed(periodic.magnitude);
+    let force = to_fixed(periodic.offset) + mul(mul(magnitude, envelope), force_norm);
+
+    mul(mul(force, to_fixed(effect.gain)), direction_scale(effect))
+}
+
+// Waveform of a periodic effect type, as a function of the time within a period (0 to ONE)
+pub(crate) fn waveform(effect_type: EffectType) -> fn(Fixed) -> Fixed {
+    match effect_type {
+        EffectType::Square => square_fn,
+        EffectType::Sine => sine_fn,
+        EffectType::Triangle => triangle_fn,
+        EffectType::SawtoothUp => sawtooth_up_fn,
+        EffectType::SawtoothDown => sawtooth_down_fn,
+        _ => |_| 0,
+    }
+}
+
+fn square_fn(time: Fixed) -> Fixed {
+    if time >= HALF {
+        ONE
+    } else {
+        -ONE
+    }
+}
+
+fn sine_fn(time: Fixed) -> Fixed {
+    const LUT_SAMPLES: usize = 64;
+    const SIN_LUT: [i16; LUT_SAMPLES + 1] = [
+        0, 804, 1607, 2410, 3211, 4011, 4807, 5601, 6392, 7179, 7961, 8739, 9511, 10278, 11038,
+        11792, 12539, 13278, 14009, 14732, 15446, 16150, 16845, 17530, 18204, 18867, 19519, 20159,
+        20787, 21402, 22004, 22594, 23169, 23731, 24278, 24811, 25329, 25831, 26318, 26789, 27244,
+        27683, 28105, 28510, 28897, 29268, 29621, 29955, 30272, 30571, 30851, 31113, 31356, 31580,
+        31785, 31970, 32137, 32284, 32412, 32520, 32609, 32678, 32727, 32757, 32767,
+    ];
+
+    // Index in the quarter wave table of a time within a quarter period
+    let index = |time: Fixed| (time as usize * 4 * LUT_SAMPLES) >> FRACTION_BITS;
+
+    let force_i16 = match time / QUARTER {
+        0 => SIN_LUT[index(time)],
+        1 => SIN_LUT[index(HALF - time)],
+        2 => -SIN_LUT[index(time - HALF)],
+        _ => -SIN_LUT[index(ONE - time)],
+    };
+
+    force_i16 as Fixed * ONE / i16::MAX as Fixed
+}
+
+fn triangle_fn(time: Fixed) -> Fixed {
+    if time < QUARTER {
+        4 * time
+    } else if time < 3 * QUARTER {
+        2 * ONE - 4 * time
+    } else {
+        4 * time - 4 * ONE
+    }
+}
+
+fn sawtooth_up_fn(time: Fixed) -> Fixed {
+    2 * if time < HALF { time } else { time - ONE }
+}
+
+fn sawtooth_down_fn(time: Fixed) -> Fixed {
+    -sawtooth_up_fn(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{effect::EffectParameter, ffb};
+
+    // Largest difference allowed between the fixed and floating point implementations
+    const MAX_ERROR: f32 = 1e-3;
+
+    fn assert_equivalent(effect: &Effect, time: u32, metric: f32, custom_data: &[u8]) {
+        let fixed = calculate_force_feedback(effect, time, metric, metric, metric, custom_data);
+        let float =
+            ffb::calculate_force_feedback(effect, time, metric, metric, metric, custom_data);
+        assert!(
+            (fixed - float).abs() <= MAX_ERROR,
+            "time {}, metric {}: fixed {}, float {}",
+            time,
+            metric,
+            fixed,
+            float
+        );
+    }
+
+    fn effect(effect_type: EffectType, parameter_1: EffectParameter) -> Effect {
+        Effect {
+            effect_report: Some(SetEffect {
+                effect_type,
+                duration: Some(1000),
+                sample_period: Some(7),
+                gain: 0.8,
+                ..Default::default()
+            }),
+            parameter_1: Some(parameter_1),
+            parameter_2: Some(EffectParameter::Envelope(SetEnvelope {
+                effect_block_index: 1,
+                attack_level: 0.2,
+                fade_level: 0.1,
+                attack_time: 300,
+                fade_time: 200,
+            })),
+        }
+    }
+
+    fn condition(coefficient: f32, saturation: f32) -> EffectParameter {
+        EffectParameter::Condition(SetCondition {
+            cp_offset: 0.1,
+            positive_coefficient: coefficient,
+            negative_coefficient: coefficient * 0.5,
+            positive_saturation: saturation,
+            negative_saturation: saturation,
+            dead_band: 0.05,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn constant_force_matches_floating_point() {
+        for magnitude in [-1.0, -0.37, 0.0, 0.5, 1.0] {
+            let effect = effect(
+                EffectType::ConstantForce,
+                EffectParameter::ConstantForce(SetConstantForce {
+                    effect_block_index: 1,
+                    magnitude,
+                }),
+            );
+            for time in (0..=1000).step_by(13) {
+                assert_equivalent(&effect, time, 0.0, &[]);
+            }
+        }
+    }
+
+    #[test]
+    fn ramp_force_matches_floating_point() {
+        let effect = effect(
+            EffectType::Ramp,
+            EffectParameter::RampForce(SetRampForce {
+                effect_block_index: 1,
+                ramp_start: -0.9,
+                ramp_end: 0.6,
+            }),
+        );
+        for time in (0..=1000).step_by(7) {
+            assert_equivalent(&effect, time, 0.0, &[]);
+        }
+    }
+
+    #[test]
+    fn periodic_forces_match_floating_point() {
+        for effect_type in [
+            EffectType::Square,
+            EffectType::Sine,
+            EffectType::Triangle,
+            EffectType::SawtoothUp,
+            EffectType::SawtoothDown,
+        ] {
+            let effect = effect(
+                effect_type,
+                EffectParameter::Periodic(SetPeriodic {
+                    effect_block_index: 1,
+                    magnitude: 0.7,
+                    offset: -0.2,
+                    phase: 4_500,
+                    period: 160,
+                }),
+            );
+            for time in (0..=1000).step_by(3) {
+                assert_equivalent(&effect, time, 0.0, &[]);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_force_matches_floating_point() {
+        let effect = effect(
+            EffectType::CustomForceData,
+            EffectParameter::CustomForce(SetCustomForce {
+                effect_block_index: 1,
+                custom_force_data_offset: 0,
+                sample_count: 4,
+            }),
+        );
+        let data = [0, 0, 127, 0, (-64_i8) as u8, 0, (-127_i8) as u8, 0];
+        for time in 0..=1000 {
+            assert_equivalent(&effect, time, 0.0, &data);
+        }
+    }
+
+    #[test]
+    fn conditions_match_floating_point() {
+        for (effect_type, coefficient) in [
+            (EffectType::Spring, 1.0),
+            (EffectType::Damper, 0.3),
+            (EffectType::Inertia, 0.05),
+            (EffectType::Friction, 0.8),
+        ] {
+            let effect = effect(effect_type, condition(coefficient, 0.9));
+            for i in -200..=200 {
+                assert_equivalent(&effect, 0, i as f32 * 0.01, &[]);
+            }
+        }
+    }
+
+    #[test]
+    fn direction_matches_floating_point() {
+        let mut effect = effect(
+            EffectType::ConstantForce,
+            EffectParameter::ConstantForce(SetConstantForce {
+                effect_block_index: 1,
+                magnitude: 1.0,
+            }),
+        );
+        for (direction_enable, axes) in [(true, (true, true)), (false, (true, true))] {
+            let effect_report = effect.effect_report.as_mut().unwrap();
+            effect_report.direction_enable = direction_enable;
+            effect_report.axis_x_enable = axes.0;
+            effect_report.axis_y_enable = axes.1;
+            for direction in 0..=255 {
+                effect.effect_report.as_mut().unwrap().direction_instance_1 = direction;
+                assert_equivalent(&effect, 500, 0.0, &[]);
+            }
+        }
+
+        // Cartesian directions have a second component
+        for y in (0..=255).step_by(5) {
+            for x in 0..=255 {
+                let effect_report = effect.effect_report.as_mut().unwrap();
+                effect_report.direction_instance_1 = x;
+                effect_report.direction_instance_2 = y;
+                assert_equivalent(&effect, 500, 0.0, &[]);
+            }
+        }
+    }
+
+    #[test]
+    fn force_after_duration_is_zero() {
+        let effect = effect(EffectType::Spring, condition(1.0, 1.0));
+
+        assert_eq!(
+            calculate_force_feedback(&effect, 1001, 1.0, 0.0, 0.0, &[]),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,295 @@
+// Filters applied to the final torque, after all forces have been summed. A filter with a
+// frequency or rate of zero (or any other value it can not use) is disabled and passes the torque
+// through unchanged.
+use core::f32::consts::PI;
+
+// Q of a second order Butterworth low-pass filter, which has no resonance peak
+const BUTTERWORTH_Q: f32 = 0.707_106_77;
+
+#[derive(Clone, Copy, Default)]
+pub struct FilterConfig {
+    pub low_pass_cutoff_hz: f32,
+    pub notch_frequency_hz: f32,
+    pub notch_q: f32,
+    // Largest change of the torque per second, in full scale torque
+    pub slew_rate_limit: f32,
+    pub sample_rate_hz: f32,
+}
+
+// Second order IIR filter, coefficients from the Audio EQ Cookbook
+#[derive(Clone, Copy)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+#[derive(Clone, Copy)]
+pub struct SlewRateLimiter {
+    max_step: f32,
+    previous: f32,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct OutputFilter {
+    low_pass: Option<Biquad>,
+    notch: Option<Biquad>,
+    slew_rate: Option<SlewRateLimiter>,
+}
+
+// Taylor series, accurate to the f32 resolution for 0..=PI. Only used when the filters are
+// configured.
+fn sin_cos(x: f32) -> (f32, f32) {
+    let mut sin = 0.0;
+    let mut cos = 0.0;
+    let mut term = 1.0;
+    for n in 0..20 {
+        match n % 4 {
+            0 => cos += term,
+            1 => sin += term,
+            2 => cos -= term,
+            _ => sin -= term,
+        }
+        term *= x / (n + 1) as f32;
+    }
+    (sin, cos)
+}
+
+impl Biquad {
+    // The frequency has to be below the Nyquist frequency
+    fn angular_frequency(frequency_hz: f32, sample_rate_hz: f32) -> Option<f32> {
+        if frequency_hz > 0.0 && frequency_hz < sample_rate_hz / 2.0 {
+            Some(2.0 * PI * frequency_hz / sample_rate_hz)
+        } else {
+            None
+        }
+    }
+
+    fn new(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn low_pass(cutoff_hz: f32, sample_rate_hz: f32) -> Option<Self> {
+        let w0 = Self::angular_frequency(cutoff_hz, sample_rate_hz)?;
+        let (sin, cos) = sin_cos(w0);
+        let alpha = sin / (2.0 * BUTTERWORTH_Q);
+
+        Some(Self::new(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        ))
+    }
+
+    pub fn notch(frequency_hz: f32, q: f32, sample_rate_hz: f32) -> Option<Self> {
+        let w0 = Self::angular_frequency(frequency_hz, sample_rate_hz)?;
+        let (sin, cos) = sin_cos(w0);
+        let alpha = if q > 0.0 {
+            sin / (2.0 * q)
+        } else {
+            return None;
+        };
+
+        Some(Self::new(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        ))
+    }
+
+    pub fn apply(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+impl SlewRateLimiter {
+    pub fn new(slew_rate_limit: f32, sample_rate_hz: f32) -> Option<Self> {
+        if slew_rate_limit > 0.0 && sample_rate_hz > 0.0 {
+            Some(Self {
+                max_step: slew_rate_limit / sample_rate_hz,
+                previous: 0.0,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn apply(&mut self, input: f32) -> f32 {
+        let step = f32::max(
+            f32::min(input - self.previous, self.max_step),
+            -self.max_step,
+        );
+        self.previous += step;
+        self.previous
+    }
+}
+
+impl OutputFilter {
+    pub fn new(config: &FilterConfig) -> Self {
+        Self {
+            low_pass: Biquad::low_pass(config.low_pass_cutoff_hz, config.sample_rate_hz),
+            notch: Biquad::notch(
+                config.notch_frequency_hz,
+                config.notch_q,
+                config.sample_rate_hz,
+            ),
+            slew_rate: SlewRateLimiter::new(config.slew_rate_limit, config.sample_rate_hz),
+        }
+    }
+
+    // Filters one torque sample, has to be called once per sample period
+    pub fn apply(&mut self, torque: f32) -> f32 {
+        let mut torque = torque;
+        if let Some(low_pass) = self.low_pass.as_mut() {
+            torque = low_pass.apply(torque);
+        }
+        if let Some(notch) = self.notch.as_mut() {
+            torque = notch.apply(torque);
+        }
+        if let Some(slew_rate) = self.slew_rate.as_mut() {
+            torque = slew_rate.apply(torque);
+        }
+        torque
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    const SAMPLE_RATE_HZ: f32 = 1000.0;
+
+    fn filter(
+        low_pass_cutoff_hz: f32,
+        notch_frequency_hz: f32,
+        notch_q: f32,
+        slew_rate_limit: f32,
+    ) -> OutputFilter {
+        OutputFilter::new(&FilterConfig {
+            low_pass_cutoff_hz,
+            notch_frequency_hz,
+            notch_q,
+            slew_rate_limit,
+            sample_rate_hz: SAMPLE_RATE_HZ,
+        })
+    }
+
+    // Amplitude of the output for a sine input of unit amplitude, after the filter has settled
+    fn gain_at(mut filter: OutputFilter, frequency_hz: f32) -> f32 {
+        let mut max: f32 = 0.0;
+        let mut min: f32 = 0.0;
+        for n in 0..4000 {
+            let t = n as f32 / SAMPLE_RATE_HZ;
+            let output = filter.apply((2.0 * PI * frequency_hz * t).sin());
+            if n >= 2000 {
+                max = max.max(output);
+                min = min.min(output);
+            }
+        }
+        (max - min) / 2.0
+    }
+
+    #[test]
+    fn sin_cos_matches_std() {
+        for n in 0..=100 {
+            let x = n as f32 * PI / 100.0;
+            let (sin, cos) = sin_cos(x);
+            assert!((sin - x.sin()).abs() < 1e-5);
+            assert!((cos - x.cos()).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn disabled_filters_pass_torque_through() {
+        let mut output_filter = filter(0.0, 0.0, 0.0, 0.0);
+        for input in [0.0, 1.0, -0.5, 0.25] {
+            assert_eq!(output_filter.apply(input), input);
+        }
+
+        // Parameters above the Nyquist frequency or not a number also disable the filter
+        let mut output_filter = filter(600.0, f32::NAN, 1.0, f32::NAN);
+        assert_eq!(output_filter.apply(0.75), 0.75);
+    }
+
+    #[test]
+    fn low_pass_frequency_response() {
+        let low_pass = filter(50.0, 0.0, 0.0, 0.0);
+
+        assert!((gain_at(low_pass, 2.0) - 1.0).abs() < 0.01);
+        assert!((gain_at(low_pass, 50.0) - BUTTERWORTH_Q).abs() < 0.02);
+        assert!(gain_at(low_pass, 200.0) < 0.07);
+        assert!(gain_at(low_pass, 400.0) < 0.01);
+    }
+
+    #[test]
+    fn low_pass_has_unity_dc_gain() {
+        let mut low_pass = filter(20.0, 0.0, 0.0, 0.0);
+        let mut output = 0.0;
+        for _ in 0..1000 {
+            output = low_pass.apply(0.6);
+        }
+
+        assert!((output - 0.6).abs() < 1e-4);
+    }
+
+    #[test]
+    fn notch_frequency_response() {
+        let notch = filter(0.0, 100.0, 2.0, 0.0);
+
+        assert!(gain_at(notch, 100.0) < 0.02);
+        // The bandwidth between the -3 dB points is the frequency divided by Q
+        assert!((gain_at(notch, 80.0) - BUTTERWORTH_Q).abs() < 0.03);
+        assert!((gain_at(notch, 125.0) - BUTTERWORTH_Q).abs() < 0.03);
+        assert!(gain_at(notch, 10.0) > 0.98);
+        assert!(gain_at(notch, 250.0) > 0.95);
+    }
+
+    #[test]
+    fn slew_rate_limits_steps() {
+        let mut slew_rate = filter(0.0, 0.0, 0.0, 100.0);
+
+        // 100 full scale per second is 0.1 per sample at 1 kHz
+        assert!((slew_rate.apply(1.0) - 0.1).abs() < 1e-6);
+        assert!((slew_rate.apply(1.0) - 0.2).abs() < 1e-6);
+        for _ in 0..8 {
+            slew_rate.apply(1.0);
+        }
+        assert!((slew_rate.apply(1.0) - 1.0).abs() < 1e-6);
+        assert!((slew_rate.apply(-1.0) - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn slew_rate_frequency_response() {
+        let slew_rate = filter(0.0, 0.0, 0.0, 100.0);
+
+        // A sine is only changed when its steepest slope is above the limit, above that the output
+        // becomes a triangle wave with an amplitude of a quarter period at the limited slope.
+        assert!((gain_at(slew_rate, 5.0) - 1.0).abs() < 0.01);
+        assert!((gain_at(slew_rate, 50.0) - 0.5).abs() < 0.05);
+        assert!((gain_at(slew_rate, 100.0) - 0.25).abs() < 0.05);
+    }
+
+    #[test]
+    fn filters_are_chained() {
+        let low_pass_and_notch = filter(200.0, 50.0, 2.0, 0.0);
+
+        assert!(gain_at(low_pass_and_notch, 50.0) < 0.02);
+        assert!(gain_at(low_pass_and_notch, 480.0) < 0.05);
+        assert!(gain_at(low_pass_and_notch, 5.0) > 0.95);
+    }
+}
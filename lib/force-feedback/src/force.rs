@@ -0,0 +1,122 @@
+// Numbers forces are rendered with. Without the fixed-point feature forces are floating point
+// numbers, with it they are the Q16.16 fixed point numbers of `ffb_fixed`, so that effects can
+// be rendered without floating point calculations on targets without a floating point unit.
+#[cfg(feature = "fixed-point")]
+use crate::ffb_fixed::{self, Fixed};
+
+#[cfg(not(feature = "fixed-point"))]
+pub type Force = f32;
+#[cfg(feature = "fixed-point")]
+pub type Force = Fixed;
+
+// Reciprocal of a time in milliseconds, which turns the division by the time every tick into a
+// multiplication. In fixed point it is a Q32 number, to keep the precision for long times.
+#[cfg(not(feature = "fixed-point"))]
+pub type Reciprocal = f32;
+#[cfg(feature = "fixed-point")]
+pub type Reciprocal = u64;
+
+#[cfg(not(feature = "fixed-point"))]
+pub const ONE: Force = 1.0;
+#[cfg(feature = "fixed-point")]
+pub const ONE: Force = ffb_fixed::ONE;
+
+#[cfg(not(feature = "fixed-point"))]
+pub const ZERO: Force = 0.0;
+#[cfg(feature = "fixed-point")]
+pub const ZERO: Force = 0;
+
+#[cfg(not(feature = "fixed-point"))]
+pub fn from_f32(value: f32) -> Force {
+    value
+}
+
+#[cfg(feature = "fixed-point")]
+pub fn from_f32(value: f32) -> Force {
+    ffb_fixed::to_fixed(value)
+}
+
+#[cfg(not(feature = "fixed-point"))]
+pub fn to_f32(force: Force) -> f32 {
+    force
+}
+
+#[cfg(feature = "fixed-point")]
+pub fn to_f32(force: Force) -> f32 {
+    ffb_fixed::to_f32(force)
+}
+
+#[cfg(not(feature = "fixed-point"))]
+pub fn mul(a: Force, b: Force) -> Force {
+    a * b
+}
+
+#[cfg(feature = "fixed-point")]
+pub fn mul(a: Force, b: Force) -> Force {
+    ffb_fixed::mul(a, b)
+}
+
+// A time of zero has the reciprocal of one millisecond, which keeps the fractions finite. The
+// fixed point reciprocal is rounded up, so that exact fractions like one half are not rounded
+// down to just below them.
+#[cfg(not(feature = "fixed-point"))]
+pub fn reciprocal(time: u32) -> Reciprocal {
+    1.0 / u32::max(time, 1) as f32
+}
+
+#[cfg(feature = "fixed-point")]
+pub fn reciprocal(time: u32) -> Reciprocal {
+    let time = u32::max(time, 1) as u64;
+    (1_u64 << 32).div_ceil(time)
+}
+
+// `time` divided by the time the reciprocal was calculated for
+#[cfg(not(feature = "fixed-point"))]
+pub fn fraction(time: u32, reciprocal: Reciprocal) -> Force {
+    time as f32 * reciprocal
+}
+
+#[cfg(feature = "fixed-point")]
+pub fn fraction(time: u32, reciprocal: Reciprocal) -> Force {
+    let fraction = (time as u64).saturating_mul(reciprocal) >> (32 - ffb_fixed::FRACTION_BITS);
+    ffb_fixed::saturate(fraction as i64)
+}
+
+// Force of a custom force sample, where i8::MAX is full force
+#[cfg(not(feature = "fixed-point"))]
+pub fn from_sample(sample: i8) -> Force {
+    sample as f32 * (1.0 / i8::MAX as f32)
+}
+
+#[cfg(feature = "fixed-point")]
+pub fn from_sample(sample: i8) -> Force {
+    sample as Fixed * ONE / i8::MAX as Fixed
+}
+
+pub fn abs(force: Force) -> Force {
+    if force < ZERO {
+        -force
+    } else {
+        force
+    }
+}
+
+pub fn min(a: Force, b: Force) -> Force {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+pub fn max(a: Force, b: Force) -> Force {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+pub fn clamp(force: Force, min: Force, max: Force) -> Force {
+    self::max(self::min(force, max), min)
+}
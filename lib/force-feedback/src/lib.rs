@@ -1,5 +1,13 @@
 #![no_std]
 
+pub mod effect;
 pub mod ffb;
+#[cfg(any(feature = "fixed-point", test))]
+pub mod ffb_fixed;
+pub mod filter;
+pub mod force;
+pub mod oscillation;
+pub mod reconstruction;
+pub mod render_state;
+pub mod renderer;
 pub mod reports;
-pub mod effect;
@@ -0,0 +1,152 @@
+// Detects the wheel oscillating around the center, which happens when the driver lets go of a
+// wheel with a strong centering force. The wheel is oscillating when it has crossed the center
+// fast enough, often enough within a time window.
+
+// Distance from the center the wheel has to reach on the other side to count as a crossing, so
+// that noise around the center is not counted.
+const CROSSING_HYSTERESIS: f32 = 0.01;
+// Number of crossings remembered, more min crossings are never reached
+pub const MAX_CROSSINGS: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct OscillationConfig {
+    pub window_ms: u32,
+    // Zero disables the detection
+    pub min_crossings: u8,
+    // Smallest steering velocity for a crossing to count, in steering units per second
+    pub min_velocity: f32,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct OscillationDetector {
+    time: u32,
+    side: i8,
+    crossing_times: [u32; MAX_CROSSINGS],
+    crossing_count: usize,
+    oscillating: bool,
+}
+
+impl OscillationDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(
+        &mut self,
+        position: f32,
+        velocity: f32,
+        delta_time_ms: u32,
+        config: &OscillationConfig,
+    ) -> bool {
+        self.time = self.time.wrapping_add(delta_time_ms);
+
+        let side = if position > CROSSING_HYSTERESIS {
+            1
+        } else if position < -CROSSING_HYSTERESIS {
+            -1
+        } else {
+            self.side
+        };
+
+        if side != self.side && self.side != 0 && f32::abs(velocity) >= config.min_velocity {
+            self.crossing_times[self.crossing_count % MAX_CROSSINGS] = self.time;
+            self.crossing_count += 1;
+        }
+        self.side = side;
+
+        let recent_crossings = self.crossing_times
+            [..usize::min(self.crossing_count, MAX_CROSSINGS)]
+            .iter()
+            .filter(|time| self.time.wrapping_sub(**time) <= config.window_ms)
+            .count();
+        self.oscillating =
+            config.min_crossings > 0 && recent_crossings >= config.min_crossings as usize;
+        self.oscillating
+    }
+
+    pub fn oscillating(&self) -> bool {
+        self.oscillating
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use core::f32::consts::PI;
+
+    const TICK_MS: u32 = 2;
+    const CONFIG: OscillationConfig = OscillationConfig {
+        window_ms: 1000,
+        min_crossings: 4,
+        min_velocity: 0.5,
+    };
+
+    // Feeds a sine movement of the wheel to the detector, returns if it was oscillating at the end
+    fn detect(
+        detector: &mut OscillationDetector,
+        amplitude: f32,
+        frequency_hz: f32,
+        duration_ms: u32,
+    ) -> bool {
+        let mut oscillating = false;
+        for tick in 0..duration_ms / TICK_MS {
+            let t = (tick * TICK_MS) as f32 / 1000.0;
+            let position = amplitude * (2.0 * PI * frequency_hz * t).sin();
+            let velocity =
+                amplitude * 2.0 * PI * frequency_hz * (2.0 * PI * frequency_hz * t).cos();
+            oscillating = detector.update(position, velocity, TICK_MS, &CONFIG);
+        }
+        oscillating
+    }
+
+    #[test]
+    fn fast_oscillation_is_detected() {
+        let mut detector = OscillationDetector::new();
+
+        assert!(detect(&mut detector, 0.2, 3.0, 1000));
+        assert!(detector.oscillating());
+    }
+
+    #[test]
+    fn slow_steering_is_not_oscillation() {
+        let mut detector = OscillationDetector::new();
+
+        // Crosses the center often enough, but slower than the minimum velocity
+        assert!(!detect(&mut detector, 0.02, 3.0, 1000));
+        // Fast enough, but does not cross the center often enough
+        assert!(!detect(&mut detector, 0.5, 0.5, 2000));
+    }
+
+    #[test]
+    fn noise_around_center_is_not_oscillation() {
+        let mut detector = OscillationDetector::new();
+
+        assert!(!detect(&mut detector, 0.005, 20.0, 1000));
+    }
+
+    #[test]
+    fn detection_ends_after_oscillation_stops() {
+        let mut detector = OscillationDetector::new();
+        assert!(detect(&mut detector, 0.2, 3.0, 1000));
+
+        for _ in 0..(CONFIG.window_ms / TICK_MS) {
+            detector.update(0.1, 0.0, TICK_MS, &CONFIG);
+        }
+        assert!(!detector.oscillating());
+    }
+
+    #[test]
+    fn zero_min_crossings_disables_detection() {
+        let mut detector = OscillationDetector::new();
+        let config = OscillationConfig {
+            min_crossings: 0,
+            ..CONFIG
+        };
+
+        for _ in 0..(CONFIG.window_ms / TICK_MS) {
+            assert!(!detector.update(0.0, 0.0, TICK_MS, &config));
+        }
+    }
+}
@@ -0,0 +1,223 @@
+// Games update the magnitude of constant and ramp forces at a much lower rate than the forces are
+// rendered, which makes the force a staircase. When the magnitude changes, the step is spread
+// out over the time between the last two updates, so the force reaches the new value about when
+// the next update is expected. The time is limited to a maximum latency, and only the step caused
+// by the update is smoothed, changes from envelopes and ramps are rendered as is.
+use crate::{
+    force::{self, Force},
+    renderer::TypedEffect,
+};
+
+#[derive(Clone, Copy, Default)]
+pub struct ForceReconstruction {
+    // Magnitudes of the effect at the last update, None until the effect is rendered once
+    magnitudes: Option<[f32; 2]>,
+    time_since_update: u32,
+    output: Force,
+    offset: Force,
+    offset_step: Force,
+}
+
+// The magnitudes the host updates while the effect is playing
+fn magnitudes(effect: &TypedEffect) -> Option<[f32; 2]> {
+    match effect {
+        TypedEffect::ConstantForce(e) => Some([e.constant_force.magnitude, 0.0]),
+        TypedEffect::RampForce(e) => Some([e.ramp_force.ramp_start, e.ramp_force.ramp_end]),
+        _ => None,
+    }
+}
+
+impl ForceReconstruction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the force to output for the force rendered from the current parameters of the
+    // effect. Has to be called every update, a max latency of zero disables the reconstruction.
+    pub fn apply(
+        &mut self,
+        effect: &TypedEffect,
+        force: Force,
+        delta_time_ms: u32,
+        max_latency_ms: u32,
+    ) -> Force {
+        let magnitudes = match magnitudes(effect) {
+            Some(magnitudes) if max_latency_ms > 0 => magnitudes,
+            _ => return force,
+        };
+
+        self.time_since_update += delta_time_ms;
+        match self.magnitudes {
+            Some(previous) if previous != magnitudes => {
+                let duration = u32::min(self.time_since_update, max_latency_ms);
+                self.offset = self.output - force;
+                let step = force::fraction(delta_time_ms, force::reciprocal(duration));
+                self.offset_step = force::mul(self.offset, step);
+                self.time_since_update = 0;
+            }
+            Some(_) => {}
+            None => self.time_since_update = 0,
+        }
+        self.magnitudes = Some(magnitudes);
+
+        // Move towards the force of the new magnitudes, without overshooting it
+        if force::abs(self.offset) > force::abs(self.offset_step) {
+            self.offset -= self.offset_step;
+        } else {
+            self.offset = force::ZERO;
+        }
+
+        self.output = force + self.offset;
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        renderer::{ConstantForceEffect, PeriodicEffect, RampForceEffect},
+        reports::{SetConstantForce, SetEffect, SetPeriodic, SetRampForce},
+    };
+
+    const TICK_MS: u32 = 2;
+
+    // Fixed point steps are rounded, so the force can be a fraction of a step from its target
+    const MAX_ERROR: f32 = if cfg!(feature = "fixed-point") {
+        1e-4
+    } else {
+        1e-6
+    };
+
+    // Applies the reconstruction to a force given as a floating point number
+    fn apply(
+        reconstruction: &mut ForceReconstruction,
+        effect: &TypedEffect,
+        force: f32,
+        max_latency_ms: u32,
+    ) -> f32 {
+        let force = force::from_f32(force);
+        force::to_f32(reconstruction.apply(effect, force, TICK_MS, max_latency_ms))
+    }
+
+    fn constant(magnitude: f32) -> TypedEffect {
+        TypedEffect::ConstantForce(ConstantForceEffect {
+            effect: SetEffect::default(),
+            constant_force: SetConstantForce {
+                effect_block_index: 1,
+                magnitude,
+            },
+            envelope: None,
+        })
+    }
+
+    // Renders a constant force whose magnitude is updated with a step sequence, each magnitude
+    // is held for `interval_ms`.
+    fn render_steps(steps: &[f32], interval_ms: u32, max_latency_ms: u32) -> ([f32; 512], usize) {
+        let mut reconstruction = ForceReconstruction::new();
+        let mut output = [0.0; 512];
+        let mut count = 0;
+        for magnitude in steps {
+            for _ in 0..interval_ms / TICK_MS {
+                let effect = constant(*magnitude);
+                output[count] = apply(&mut reconstruction, &effect, *magnitude, max_latency_ms);
+                count += 1;
+            }
+        }
+        (output, count)
+    }
+
+    #[test]
+    fn disabled_reconstruction_outputs_steps() {
+        let (output, count) = render_steps(&[0.0, 1.0, 0.5], 16, 0);
+
+        assert_eq!(count, 24);
+        assert_eq!(output[7], 0.0);
+        assert_eq!(output[8], 1.0);
+        assert_eq!(output[16], 0.5);
+    }
+
+    #[test]
+    fn first_magnitude_is_not_delayed() {
+        let (output, _) = render_steps(&[0.8], 16, 50);
+
+        assert!((output[0] - 0.8).abs() < MAX_ERROR);
+    }
+
+    #[test]
+    fn step_is_spread_over_update_interval() {
+        let (output, _) = render_steps(&[0.0, 1.0, 1.0], 16, 50);
+
+        // The step arrives at tick 8 and is reached one update interval later
+        assert!(output[8] > 0.0 && output[8] < 0.2);
+        for tick in 8..15 {
+            assert!(output[tick + 1] > output[tick]);
+        }
+        assert!((output[15] - 1.0).abs() < MAX_ERROR);
+        assert_eq!(output[23], 1.0);
+    }
+
+    #[test]
+    fn latency_is_bounded() {
+        let (output, _) = render_steps(&[0.0, 1.0], 100, 20);
+
+        // Updates are 100 ms apart, but the step is reached after 20 ms
+        assert!(output[50] < 1.0);
+        assert!((output[59] - 1.0).abs() < MAX_ERROR);
+    }
+
+    #[test]
+    fn staircase_is_smoothed() {
+        let steps = [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0, 1.0];
+        let (output, count) = render_steps(&steps, 16, 50);
+
+        // Without reconstruction the force jumps 0.1 every 8 ticks, now it rises every tick
+        let max_change = (1..count)
+            .map(|tick| (output[tick] - output[tick - 1]).abs())
+            .fold(0.0, f32::max);
+        assert!(max_change < 0.1 * TICK_MS as f32 / 16.0 + 1e-4);
+        assert!((output[count - 1] - 1.0).abs() < MAX_ERROR);
+    }
+
+    #[test]
+    fn ramp_magnitude_updates_are_reconstructed() {
+        let mut reconstruction = ForceReconstruction::new();
+        let ramp = |ramp_end: f32| {
+            TypedEffect::RampForce(RampForceEffect {
+                effect: SetEffect::default(),
+                ramp_force: SetRampForce {
+                    effect_block_index: 1,
+                    ramp_start: 0.0,
+                    ramp_end,
+                },
+                envelope: None,
+            })
+        };
+
+        assert_eq!(apply(&mut reconstruction, &ramp(0.5), 0.25, 50), 0.25);
+        assert_eq!(apply(&mut reconstruction, &ramp(0.5), 0.375, 50), 0.375);
+
+        // A change of the ramp itself is passed through, an update of its magnitudes is smoothed
+        let output = apply(&mut reconstruction, &ramp(1.0), 0.75, 50);
+        assert!(output > 0.375 && output < 0.75);
+    }
+
+    #[test]
+    fn other_effects_are_not_reconstructed() {
+        let mut reconstruction = ForceReconstruction::new();
+        let periodic = TypedEffect::Periodic(PeriodicEffect {
+            effect: SetEffect::default(),
+            periodic: SetPeriodic {
+                effect_block_index: 1,
+                magnitude: 1.0,
+                offset: 0.0,
+                phase: 0,
+                period: 100,
+            },
+            envelope: None,
+        });
+
+        assert_eq!(apply(&mut reconstruction, &periodic, 0.0, 50), 0.0);
+        assert_eq!(apply(&mut reconstruction, &periodic, 1.0, 50), 1.0);
+    }
+}
@@ -0,0 +1,523 @@
+// Effects compiled into the values needed every tick, so that rendering an effect only takes a
+// few multiply-adds. Everything that only depends on the parameter blocks, like the effect gain,
+// the direction, envelope slopes and clamping limits, is calculated once when the effect is built.
+// With the fixed-point feature the values are converted to fixed point once, and effects are
+// rendered without floating point calculations.
+#[cfg(not(feature = "fixed-point"))]
+use crate::ffb as engine;
+#[cfg(feature = "fixed-point")]
+use crate::ffb_fixed as engine;
+use crate::{
+    ffb::CUSTOM_FORCE_SAMPLE_SIZE,
+    force::{self, Force, Reciprocal},
+    renderer::{EffectRenderer, SteeringState, TypedEffect},
+    reports::{EffectType, SetEnvelope},
+};
+
+#[derive(Clone, Copy)]
+pub struct RenderState {
+    effect: TypedEffect,
+    precomputed: Precomputed,
+}
+
+#[derive(Clone, Copy)]
+struct Precomputed {
+    duration: Option<u32>,
+    envelope: Option<EnvelopeState>,
+    force: ForceState,
+}
+
+#[derive(Clone, Copy)]
+struct EnvelopeState {
+    attack_time: u32,
+    attack_level: Force,
+    attack_range: Force,
+    attack_reciprocal: Reciprocal,
+    fade_time: u32,
+    fade_level: Force,
+    fade_range: Force,
+    fade_reciprocal: Reciprocal,
+}
+
+#[derive(Clone, Copy)]
+enum Metric {
+    Position,
+    Velocity,
+    Acceleration,
+}
+
+// The forces are already scaled by the effect gain and direction
+#[derive(Clone, Copy)]
+enum ForceState {
+    Zero,
+    Constant {
+        force: Force,
+    },
+    Ramp {
+        start: Force,
+        range: Force,
+        reciprocal: Reciprocal,
+    },
+    Periodic {
+        waveform: fn(Force) -> Force,
+        phase_time: u32,
+        period: u32,
+        reciprocal: Reciprocal,
+        magnitude: Force,
+        offset: Force,
+    },
+    Condition {
+        metric: Metric,
+        lower: Force,
+        upper: Force,
+        negative_coefficient: Force,
+        positive_coefficient: Force,
+        min: Force,
+        max: Force,
+    },
+    Friction {
+        lower: Force,
+        upper: Force,
+        slip_scale: Force,
+        negative_coefficient: Force,
+        positive_coefficient: Force,
+        min: Force,
+        max: Force,
+    },
+    Custom {
+        offset: usize,
+        sample_count: u32,
+        sample_period: u32,
+        reciprocal: Reciprocal,
+        scale: Force,
+    },
+}
+
+impl EnvelopeState {
+    fn new(envelope: &SetEnvelope) -> Self {
+        Self {
+            attack_time: envelope.attack_time,
+            attack_level: force::from_f32(envelope.attack_level),
+            attack_range: force::from_f32(1.0 - envelope.attack_level),
+            attack_reciprocal: force::reciprocal(envelope.attack_time),
+            fade_time: envelope.fade_time,
+            fade_level: force::from_f32(envelope.fade_level),
+            fade_range: force::from_f32(1.0 - envelope.fade_level),
+            fade_reciprocal: force::reciprocal(envelope.fade_time),
+        }
+    }
+
+    fn level(&self, time: u32, duration: Option<u32>) -> Force {
+        let mut result = force::ONE;
+        if time < self.attack_time {
+            let attack = force::fraction(time, self.attack_reciprocal);
+            let attack_force = self.attack_level + force::mul(self.attack_range, attack);
+            result = force::min(result, attack_force);
+        }
+        if let Some(duration) = duration {
+            if time <= duration && time + self.fade_time > duration {
+                let fade = force::fraction(duration - time, self.fade_reciprocal);
+                let fade_force = self.fade_level + force::mul(self.fade_range, fade);
+                result = force::min(result, fade_force);
+            }
+        }
+        result
+    }
+}
+
+impl RenderState {
+    pub fn new(effect: TypedEffect) -> Self {
+        Self {
+            effect,
+            precomputed: Precomputed::new(&effect),
+        }
+    }
+
+    pub fn effect(&self) -> &TypedEffect {
+        &self.effect
+    }
+}
+
+impl Precomputed {
+    fn new(effect: &TypedEffect) -> Self {
+        let effect_report = effect.effect_report();
+        let gain = effect_report.gain;
+        let scale = force::mul(
+            force::from_f32(gain),
+            engine::direction_scale(effect_report),
+        );
+        let duration = effect_report.duration.map(|duration| duration as u32);
+
+        let (force, envelope) = match *effect {
+            TypedEffect::ConstantForce(e) => (
+                ForceState::Constant {
+                    force: force::mul(force::from_f32(e.constant_force.magnitude), scale),
+                },
+                e.envelope,
+            ),
+            TypedEffect::RampForce(e) => {
+                let force = match duration {
+                    Some(duration) => ForceState::Ramp {
+                        start: force::mul(force::from_f32(e.ramp_force.ramp_start), scale),
+                        range: force::mul(
+                            force::from_f32(e.ramp_force.ramp_end - e.ramp_force.ramp_start),
+                            scale,
+                        ),
+                        reciprocal: force::reciprocal(duration),
+                    },
+                    None => ForceState::Zero,
+                };
+                (force, e.envelope)
+            }
+            TypedEffect::Periodic(e) => {
+                let period = e.periodic.period;
+                let force = ForceState::Periodic {
+                    waveform: if period > 0 {
+                        engine::waveform(e.effect.effect_type)
+                    } else {
+                        |_| force::ZERO
+                    },
+                    phase_time: ((e.periodic.phase as u64 * period as u64) / 36_000) as u32,
+                    period: u32::max(period, 1),
+                    reciprocal: force::reciprocal(period),
+                    magnitude: force::mul(force::from_f32(e.periodic.magnitude), scale),
+                    offset: force::mul(force::from_f32(e.periodic.offset), scale),
+                };
+                (force, e.envelope)
+            }
+            TypedEffect::Condition(e) => {
+                let c = e.condition;
+                let lower = force::from_f32(c.cp_offset - c.dead_band);
+                let upper = force::from_f32(c.cp_offset + c.dead_band);
+                let negative_coefficient = force::from_f32(c.negative_coefficient * gain);
+                let positive_coefficient = force::from_f32(c.positive_coefficient * gain);
+                let min = force::from_f32(f32::min(-c.negative_saturation * gain, 0.0));
+                let max = force::from_f32(f32::max(c.positive_saturation * gain, 0.0));
+
+                let metric = match e.effect.effect_type {
+                    EffectType::Spring => Some(Metric::Position),
+                    EffectType::Damper => Some(Metric::Velocity),
+                    EffectType::Inertia => Some(Metric::Acceleration),
+                    _ => None,
+                };
+                let force = match (e.effect.effect_type, metric) {
+                    (_, Some(metric)) => ForceState::Condition {
+                        metric,
+                        lower,
+                        upper,
+                        negative_coefficient,
+                        positive_coefficient,
+                        min,
+                        max,
+                    },
+                    (EffectType::Friction, None) => ForceState::Friction {
+                        lower,
+                        upper,
+                        slip_scale: force::from_f32(
+                            1.0 / force::to_f32(engine::FRICTION_TRANSITION_VELOCITY),
+                        ),
+                        negative_coefficient,
+                        positive_coefficient,
+                        min,
+                        max,
+                    },
+                    _ => ForceState::Zero,
+                };
+                (force, None)
+            }
+            TypedEffect::CustomForce(e) => {
+                let force = match (e.custom_force, e.effect.sample_period) {
+                    (Some(custom_force), Some(sample_period)) if custom_force.sample_count > 0 => {
+                        ForceState::Custom {
+                            offset: custom_force.custom_force_data_offset as usize,
+                            sample_count: custom_force.sample_count as u32,
+                            sample_period: sample_period as u32,
+                            reciprocal: force::reciprocal(sample_period as u32),
+                            scale,
+                        }
+                    }
+                    _ => ForceState::Zero,
+                };
+                (force, e.envelope)
+            }
+        };
+
+        Self {
+            duration,
+            envelope: envelope.as_ref().map(EnvelopeState::new),
+            force,
+        }
+    }
+
+    fn envelope(&self, time: u32) -> Force {
+        match self.envelope {
+            Some(envelope) => envelope.level(time, self.duration),
+            None => force::ONE,
+        }
+    }
+
+    fn render(&self, time: u32, steering: &SteeringState, custom_data: &[u8]) -> Force {
+        if let Some(duration) = self.duration {
+            if time > duration {
+                return force::ZERO;
+            }
+        }
+
+        match self.force {
+            ForceState::Zero => force::ZERO,
+            ForceState::Constant { force } => force::mul(force, self.envelope(time)),
+            ForceState::Ramp {
+                start,
+                range,
+                reciprocal,
+            } => {
+                let force = start + force::mul(range, force::fraction(time, reciprocal));
+                force::mul(force, self.envelope(time))
+            }
+            ForceState::Periodic {
+                waveform,
+                phase_time,
+                period,
+                reciprocal,
+                magnitude,
+                offset,
+            } => {
+                let t = force::fraction((time + phase_time) % period, reciprocal);
+                offset + force::mul(force::mul(magnitude, self.envelope(time)), waveform(t))
+            }
+            ForceState::Condition {
+                metric,
+                lower,
+                upper,
+                negative_coefficient,
+                positive_coefficient,
+                min,
+                max,
+            } => {
+                let metric = match metric {
+                    Metric::Position => steering.position,
+                    Metric::Velocity => steering.velocity,
+                    Metric::Acceleration => steering.acceleration,
+                };
+                let force = if metric < lower {
+                    force::mul(negative_coefficient, metric - lower)
+                } else if metric > upper {
+                    force::mul(positive_coefficient, metric - upper)
+                } else {
+                    force::ZERO
+                };
+                force::clamp(force, min, max)
+            }
+            ForceState::Friction {
+                lower,
+                upper,
+                slip_scale,
+                negative_coefficient,
+                positive_coefficient,
+                min,
+                max,
+            } => {
+                let velocity = steering.velocity;
+                let force = if velocity < lower {
+                    let slip = force::mul(velocity - lower, slip_scale);
+                    force::mul(negative_coefficient, force::max(slip, -force::ONE))
+                } else if velocity > upper {
+                    let slip = force::mul(velocity - upper, slip_scale);
+                    force::mul(positive_coefficient, force::min(slip, force::ONE))
+                } else {
+                    force::ZERO
+                };
+                force::clamp(force, min, max)
+            }
+            ForceState::Custom {
+                offset,
+                sample_count,
+                sample_period,
+                reciprocal,
+                scale,
+            } => {
+                // The samples are repeated until the effect duration has elapsed.
+                let sample_time = time % (sample_count * sample_period);
+                let sample_index = sample_time / sample_period;
+                let fraction = force::fraction(sample_time % sample_period, reciprocal);
+
+                let sample = |index: u32| {
+                    let address =
+                        offset + (index % sample_count) as usize * CUSTOM_FORCE_SAMPLE_SIZE;
+                    custom_data
+                        .get(address)
+                        .map(|s| force::from_sample(*s as i8))
+                        .unwrap_or(force::ZERO)
+                };
+
+                let force_start = sample(sample_index);
+                let force_end = sample(sample_index + 1);
+                let force = force_start + force::mul(force_end - force_start, fraction);
+                force::mul(force::mul(force, scale), self.envelope(time))
+            }
+        }
+    }
+}
+
+impl EffectRenderer for RenderState {
+    fn render(&self, time: u32, steering: &SteeringState, custom_data: &[u8]) -> Force {
+        self.precomputed.render(time, steering, custom_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        effect::{Effect, EffectParameter},
+        ffb::calculate_force_feedback,
+        reports::{
+            SetCondition, SetConstantForce, SetCustomForce, SetEffect, SetPeriodic, SetRampForce,
+        },
+    };
+
+    // Largest difference allowed between the precomputed and the direct floating point
+    // calculation, which is the same bound as for the fixed point engine with the fixed-point
+    // feature.
+    const MAX_ERROR: f32 = if cfg!(feature = "fixed-point") {
+        1e-3
+    } else {
+        1e-4
+    };
+
+    fn effect(effect_type: EffectType, parameter_1: EffectParameter) -> Effect {
+        Effect {
+            effect_report: Some(SetEffect {
+                effect_type,
+                duration: Some(1000),
+                sample_period: Some(7),
+                gain: 0.8,
+                direction_enable: true,
+                direction_instance_1: 80,
+                ..Default::default()
+            }),
+            parameter_1: Some(parameter_1),
+            parameter_2: Some(EffectParameter::Envelope(SetEnvelope {
+                effect_block_index: 1,
+                attack_level: 0.2,
+                fade_level: 0.1,
+                attack_time: 300,
+                fade_time: 200,
+            })),
+        }
+    }
+
+    fn condition_effect(effect_type: EffectType) -> Effect {
+        let mut effect = effect(
+            effect_type,
+            EffectParameter::Condition(SetCondition {
+                cp_offset: 0.1,
+                positive_coefficient: 0.9,
+                negative_coefficient: 0.4,
+                positive_saturation: 0.7,
+                negative_saturation: 0.6,
+                dead_band: 0.05,
+                ..Default::default()
+            }),
+        );
+        effect.parameter_2 = None;
+        effect
+    }
+
+    fn assert_matches(effect: &Effect, custom_data: &[u8]) {
+        let render_state = RenderState::new(TypedEffect::try_from(effect).unwrap());
+        for time in 0..=1001 {
+            let metric = (time as f32 - 500.0) / 250.0;
+            let steering = SteeringState::new(metric, metric, metric);
+
+            let expected =
+                calculate_force_feedback(effect, time, metric, metric, metric, custom_data);
+            let force = force::to_f32(render_state.render(time, &steering, custom_data));
+            assert!(
+                (force - expected).abs() <= MAX_ERROR,
+                "time {}: expected {}, got {}",
+                time,
+                expected,
+                force
+            );
+        }
+    }
+
+    #[test]
+    fn constant_force_matches_direct_calculation() {
+        let effect = effect(
+            EffectType::ConstantForce,
+            EffectParameter::ConstantForce(SetConstantForce {
+                effect_block_index: 1,
+                magnitude: -0.6,
+            }),
+        );
+
+        assert_matches(&effect, &[]);
+    }
+
+    #[test]
+    fn ramp_force_matches_direct_calculation() {
+        let effect = effect(
+            EffectType::Ramp,
+            EffectParameter::RampForce(SetRampForce {
+                effect_block_index: 1,
+                ramp_start: -0.9,
+                ramp_end: 0.6,
+            }),
+        );
+
+        assert_matches(&effect, &[]);
+    }
+
+    #[test]
+    fn periodic_forces_match_direct_calculation() {
+        for effect_type in [
+            EffectType::Square,
+            EffectType::Sine,
+            EffectType::Triangle,
+            EffectType::SawtoothUp,
+            EffectType::SawtoothDown,
+        ] {
+            let effect = effect(
+                effect_type,
+                EffectParameter::Periodic(SetPeriodic {
+                    effect_block_index: 1,
+                    magnitude: 0.7,
+                    offset: -0.2,
+                    phase: 4_500,
+                    period: 160,
+                }),
+            );
+
+            assert_matches(&effect, &[]);
+        }
+    }
+
+    #[test]
+    fn conditions_match_direct_calculation() {
+        for effect_type in [
+            EffectType::Spring,
+            EffectType::Damper,
+            EffectType::Inertia,
+            EffectType::Friction,
+        ] {
+            assert_matches(&condition_effect(effect_type), &[]);
+        }
+    }
+
+    #[test]
+    fn custom_force_matches_direct_calculation() {
+        let effect = effect(
+            EffectType::CustomForceData,
+            EffectParameter::CustomForce(SetCustomForce {
+                effect_block_index: 1,
+                custom_force_data_offset: 2,
+                sample_count: 3,
+            }),
+        );
+        let data = [0, 0, 127, 0, (-64_i8) as u8, 0, (-127_i8) as u8, 0];
+
+        assert_matches(&effect, &data);
+    }
+}
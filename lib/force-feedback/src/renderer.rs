@@ -0,0 +1,378 @@
+// Validated effects, built from the reports the host has sent for an effect. Every effect kind
+// only holds the parameter blocks it uses, and knows how to render its own force.
+#[cfg(not(feature = "fixed-point"))]
+use crate::ffb as engine;
+#[cfg(feature = "fixed-point")]
+use crate::ffb_fixed as engine;
+use crate::{
+    effect::{Effect, EffectParameter},
+    force::{self, Force},
+    reports::{
+        EffectType, SetCondition, SetConstantForce, SetCustomForce, SetEffect, SetEnvelope,
+        SetPeriodic, SetRampForce,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectError {
+    // No Set Effect Report has been received for the effect
+    MissingEffectReport,
+    // The parameter block the effect type needs has not been received
+    MissingParameter,
+    // A parameter block that can not be used with the effect type has been received
+    UnexpectedParameter,
+}
+
+// State of the steering axis the effects are rendered for, converted once for all effects
+#[derive(Clone, Copy, Default)]
+pub struct SteeringState {
+    pub position: Force,
+    pub velocity: Force,
+    pub acceleration: Force,
+}
+
+impl SteeringState {
+    pub fn new(position: f32, velocity: f32, acceleration: f32) -> Self {
+        Self {
+            position: force::from_f32(position),
+            velocity: force::from_f32(velocity),
+            acceleration: force::from_f32(acceleration),
+        }
+    }
+}
+
+// Forces are only converted to floating point by the caller, after they have been added up
+pub trait EffectRenderer {
+    // Force of the effect at `time` milliseconds after it was started
+    fn render(&self, time: u32, steering: &SteeringState, custom_data: &[u8]) -> Force;
+}
+
+#[derive(Clone, Copy)]
+pub struct ConstantForceEffect {
+    pub effect: SetEffect,
+    pub constant_force: SetConstantForce,
+    pub envelope: Option<SetEnvelope>,
+}
+
+#[derive(Clone, Copy)]
+pub struct RampForceEffect {
+    pub effect: SetEffect,
+    pub ramp_force: SetRampForce,
+    pub envelope: Option<SetEnvelope>,
+}
+
+#[derive(Clone, Copy)]
+pub struct PeriodicEffect {
+    pub effect: SetEffect,
+    pub periodic: SetPeriodic,
+    pub envelope: Option<SetEnvelope>,
+}
+
+// Only the steering axis is rendered, so the condition block of a second axis is not kept.
+#[derive(Clone, Copy)]
+pub struct ConditionEffect {
+    pub effect: SetEffect,
+    pub condition: SetCondition,
+}
+
+// Custom force effects without a Set Custom Force Report have their samples streamed with
+// Download Force Sample reports, which are rendered by the device itself.
+#[derive(Clone, Copy)]
+pub struct CustomForceEffect {
+    pub effect: SetEffect,
+    pub custom_force: Option<SetCustomForce>,
+    pub envelope: Option<SetEnvelope>,
+}
+
+#[derive(Clone, Copy)]
+pub enum TypedEffect {
+    ConstantForce(ConstantForceEffect),
+    RampForce(RampForceEffect),
+    Periodic(PeriodicEffect),
+    Condition(ConditionEffect),
+    CustomForce(CustomForceEffect),
+}
+
+impl TypedEffect {
+    pub fn effect_report(&self) -> &SetEffect {
+        match self {
+            TypedEffect::ConstantForce(e) => &e.effect,
+            TypedEffect::RampForce(e) => &e.effect,
+            TypedEffect::Periodic(e) => &e.effect,
+            TypedEffect::Condition(e) => &e.effect,
+            TypedEffect::CustomForce(e) => &e.effect,
+        }
+    }
+}
+
+// The envelope is the only optional parameter block of the non condition effects
+fn envelope(parameter: Option<EffectParameter>) -> Result<Option<SetEnvelope>, EffectError> {
+    match parameter {
+        Some(EffectParameter::Envelope(envelope)) => Ok(Some(envelope)),
+        Some(_) => Err(EffectError::UnexpectedParameter),
+        None => Ok(None),
+    }
+}
+
+impl TryFrom<&Effect> for TypedEffect {
+    type Error = EffectError;
+
+    fn try_from(effect: &Effect) -> Result<Self, Self::Error> {
+        let effect_report = effect
+            .effect_report
+            .ok_or(EffectError::MissingEffectReport)?;
+
+        match (effect_report.effect_type, effect.parameter_1) {
+            (_, Some(EffectParameter::Envelope(_))) => Err(EffectError::UnexpectedParameter),
+            (_, None) if effect_report.effect_type != EffectType::CustomForceData => {
+                Err(EffectError::MissingParameter)
+            }
+            (EffectType::ConstantForce, Some(EffectParameter::ConstantForce(constant_force))) => {
+                Ok(TypedEffect::ConstantForce(ConstantForceEffect {
+                    effect: effect_report,
+                    constant_force,
+                    envelope: envelope(effect.parameter_2)?,
+                }))
+            }
+            (EffectType::Ramp, Some(EffectParameter::RampForce(ramp_force))) => {
+                Ok(TypedEffect::RampForce(RampForceEffect {
+                    effect: effect_report,
+                    ramp_force,
+                    envelope: envelope(effect.parameter_2)?,
+                }))
+            }
+            (
+                EffectType::Square
+                | EffectType::Sine
+                | EffectType::Triangle
+                | EffectType::SawtoothUp
+                | EffectType::SawtoothDown,
+                Some(EffectParameter::Periodic(periodic)),
+            ) => Ok(TypedEffect::Periodic(PeriodicEffect {
+                effect: effect_report,
+                periodic,
+                envelope: envelope(effect.parameter_2)?,
+            })),
+            (
+                EffectType::Spring
+                | EffectType::Damper
+                | EffectType::Inertia
+                | EffectType::Friction,
+                Some(EffectParameter::Condition(condition)),
+            ) => match effect.parameter_2 {
+                Some(EffectParameter::Condition(_)) | None => {
+                    Ok(TypedEffect::Condition(ConditionEffect {
+                        effect: effect_report,
+                        condition,
+                    }))
+                }
+                Some(_) => Err(EffectError::UnexpectedParameter),
+            },
+            (EffectType::CustomForceData, None | Some(EffectParameter::CustomForce(_))) => {
+                let custom_force = match effect.parameter_1 {
+                    Some(EffectParameter::CustomForce(custom_force)) => Some(custom_force),
+                    _ => None,
+                };
+                Ok(TypedEffect::CustomForce(CustomForceEffect {
+                    effect: effect_report,
+                    custom_force,
+                    envelope: envelope(effect.parameter_2)?,
+                }))
+            }
+            _ => Err(EffectError::UnexpectedParameter),
+        }
+    }
+}
+
+impl EffectRenderer for TypedEffect {
+    fn render(&self, time: u32, steering: &SteeringState, custom_data: &[u8]) -> Force {
+        if let Some(duration) = self.effect_report().duration {
+            if time > duration as u32 {
+                return force::ZERO;
+            }
+        }
+
+        match self {
+            TypedEffect::ConstantForce(e) => e.render(time, steering, custom_data),
+            TypedEffect::RampForce(e) => e.render(time, steering, custom_data),
+            TypedEffect::Periodic(e) => e.render(time, steering, custom_data),
+            TypedEffect::Condition(e) => e.render(time, steering, custom_data),
+            TypedEffect::CustomForce(e) => e.render(time, steering, custom_data),
+        }
+    }
+}
+
+impl EffectRenderer for ConstantForceEffect {
+    fn render(&self, time: u32, _steering: &SteeringState, _custom_data: &[u8]) -> Force {
+        engine::constant_ffb(
+            &self.effect,
+            &self.constant_force,
+            self.envelope.as_ref(),
+            time,
+        )
+    }
+}
+
+impl EffectRenderer for RampForceEffect {
+    fn render(&self, time: u32, _steering: &SteeringState, _custom_data: &[u8]) -> Force {
+        engine::ramp_ffb(&self.effect, &self.ramp_force, self.envelope.as_ref(), time)
+    }
+}
+
+impl EffectRenderer for PeriodicEffect {
+    fn render(&self, time: u32, _steering: &SteeringState, _custom_data: &[u8]) -> Force {
+        engine::periodic_ffb(&self.effect, &self.periodic, self.envelope.as_ref(), time)
+    }
+}
+
+impl EffectRenderer for ConditionEffect {
+    fn render(&self, _time: u32, steering: &SteeringState, _custom_data: &[u8]) -> Force {
+        match self.effect.effect_type {
+            EffectType::Spring => {
+                engine::condition_ffb(&self.effect, &self.condition, steering.position)
+            }
+            EffectType::Damper => {
+                engine::condition_ffb(&self.effect, &self.condition, steering.velocity)
+            }
+            EffectType::Inertia => {
+                engine::condition_ffb(&self.effect, &self.condition, steering.acceleration)
+            }
+            EffectType::Friction => {
+                engine::friction_ffb(&self.effect, &self.condition, steering.velocity)
+            }
+            _ => force::ZERO,
+        }
+    }
+}
+
+impl EffectRenderer for CustomForceEffect {
+    fn render(&self, time: u32, _steering: &SteeringState, custom_data: &[u8]) -> Force {
+        match self.custom_force {
+            Some(custom_force) => engine::custom_ffb(
+                &self.effect,
+                &custom_force,
+                self.envelope.as_ref(),
+                custom_data,
+                time,
+            ),
+            None => force::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(
+        effect_type: EffectType,
+        parameter_1: Option<EffectParameter>,
+        parameter_2: Option<EffectParameter>,
+    ) -> Effect {
+        Effect {
+            effect_report: Some(SetEffect {
+                effect_type,
+                gain: 1.0,
+                ..Default::default()
+            }),
+            parameter_1,
+            parameter_2,
+        }
+    }
+
+    fn envelope() -> EffectParameter {
+        EffectParameter::Envelope(SetEnvelope {
+            effect_block_index: 1,
+            attack_level: 0.0,
+            fade_level: 0.0,
+            attack_time: 0,
+            fade_time: 0,
+        })
+    }
+
+    fn constant_force() -> EffectParameter {
+        EffectParameter::ConstantForce(SetConstantForce {
+            effect_block_index: 1,
+            magnitude: 0.5,
+        })
+    }
+
+    fn condition() -> EffectParameter {
+        EffectParameter::Condition(SetCondition::default())
+    }
+
+    fn validate(effect: &Effect) -> Result<(), EffectError> {
+        TypedEffect::try_from(effect).map(|_| ())
+    }
+
+    #[test]
+    fn complete_effects_are_valid() {
+        let constant = effect(EffectType::ConstantForce, Some(constant_force()), None);
+        let enveloped = effect(
+            EffectType::ConstantForce,
+            Some(constant_force()),
+            Some(envelope()),
+        );
+        let spring = effect(EffectType::Spring, Some(condition()), Some(condition()));
+
+        assert_eq!(validate(&constant), Ok(()));
+        assert_eq!(validate(&enveloped), Ok(()));
+        assert_eq!(validate(&spring), Ok(()));
+    }
+
+    #[test]
+    fn parameters_without_effect_report_are_incomplete() {
+        let mut periodic = effect(EffectType::Sine, None, None);
+        periodic.effect_report = None;
+        periodic.parameter_1 = Some(EffectParameter::Periodic(SetPeriodic {
+            effect_block_index: 1,
+            magnitude: 1.0,
+            offset: 0.0,
+            phase: 0,
+            period: 100,
+        }));
+
+        assert_eq!(validate(&periodic), Err(EffectError::MissingEffectReport));
+    }
+
+    #[test]
+    fn effect_without_parameter_is_incomplete() {
+        let constant = effect(EffectType::ConstantForce, None, None);
+        let enveloped = effect(EffectType::Ramp, None, Some(envelope()));
+
+        assert_eq!(validate(&constant), Err(EffectError::MissingParameter));
+        assert_eq!(validate(&enveloped), Err(EffectError::MissingParameter));
+    }
+
+    #[test]
+    fn condition_with_envelope_is_rejected() {
+        let spring = effect(EffectType::Spring, Some(condition()), Some(envelope()));
+
+        assert_eq!(validate(&spring), Err(EffectError::UnexpectedParameter));
+    }
+
+    #[test]
+    fn parameter_must_match_effect_type() {
+        let sine = effect(EffectType::Sine, Some(constant_force()), None);
+        let damper = effect(EffectType::Damper, Some(constant_force()), None);
+
+        assert_eq!(validate(&sine), Err(EffectError::UnexpectedParameter));
+        assert_eq!(validate(&damper), Err(EffectError::UnexpectedParameter));
+    }
+
+    #[test]
+    fn streamed_custom_force_is_valid() {
+        let custom = effect(EffectType::CustomForceData, None, None);
+
+        assert_eq!(validate(&custom), Ok(()));
+    }
+
+    #[test]
+    fn typed_effect_renders_its_kind() {
+        let constant = effect(EffectType::ConstantForce, Some(constant_force()), None);
+        let typed = TypedEffect::try_from(&constant).unwrap();
+
+        let force = typed.render(0, &SteeringState::default(), &[]);
+        assert_eq!(force::to_f32(force), 0.5);
+    }
+}
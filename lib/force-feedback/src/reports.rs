@@ -8,6 +8,8 @@ pub struct RacingWheelState {
     pub steering: f32,
     pub throttle: f32,
     pub ffb: f32,
+    // How much of the hands-off oscillation suppression is applied, 0 to 1
+    pub oscillation_suppression: f32,
 }
 
 // PID State Report
@@ -95,7 +97,6 @@ impl TryFrom<u8> for EffectType {
     }
 }
 
-
 // Set Envelope Report
 #[derive(Clone, Copy)]
 pub struct SetEnvelope {
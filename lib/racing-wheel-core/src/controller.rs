@@ -0,0 +1,202 @@
+// Connects the racing wheel to the hardware. The firmware polls USB and calls update from its
+// main loop, everything else is handled here.
+use crate::{
+    hardware::{Clock, ConfigStorage, Encoder, Motor},
+    racing_wheel::RacingWheel,
+};
+use config::config::Config;
+
+pub struct Controller<E, M, C, S> {
+    encoder: E,
+    motor: M,
+    clock: C,
+    config_storage: S,
+    update_period_ms: u32,
+}
+
+impl<E: Encoder, M: Motor, C: Clock, S: ConfigStorage> Controller<E, M, C, S> {
+    pub fn new(encoder: E, motor: M, mut clock: C, config_storage: S, config: &Config) -> Self {
+        let update_period_ms = 1_000 / config.update_frequency_hz as u32;
+        clock.start(update_period_ms);
+
+        Self {
+            encoder,
+            motor,
+            clock,
+            config_storage,
+            update_period_ms,
+        }
+    }
+
+    // Handles the events from the host and advances the racing wheel once every update period.
+    // Returns true when the input reports should be sent to the host.
+    pub fn update(&mut self, racing_wheel: &mut RacingWheel, buttons: [bool; 8]) -> bool {
+        if racing_wheel.write_config_event() {
+            self.config_storage.write(&racing_wheel.get_config());
+        }
+
+        if racing_wheel.reset_steering_event() {
+            self.encoder.reset();
+        }
+
+        let mut send_input_reports = racing_wheel.pid_state_event();
+
+        if self.clock.wait() {
+            racing_wheel.set_steering(self.encoder.angle());
+            racing_wheel.set_buttons(buttons);
+
+            racing_wheel.advance(self.update_period_ms);
+
+            let config = racing_wheel.get_config();
+            self.motor.set_speed(
+                racing_wheel.get_force_feedback(),
+                (config.motor_min, config.motor_max),
+                config.motor_deadband,
+            );
+
+            send_input_reports = true;
+        }
+
+        send_input_reports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::racing_wheel::tests::{send_wheel_device_control, set_device_gain, test_config};
+    use config::control::WheelDeviceControl;
+
+    #[derive(Default)]
+    struct TestEncoder {
+        angle: f32,
+        resets: u32,
+    }
+
+    impl Encoder for TestEncoder {
+        fn angle(&mut self) -> f32 {
+            self.angle
+        }
+
+        fn reset(&mut self) {
+            self.angle = 0.0;
+            self.resets += 1;
+        }
+    }
+
+    #[derive(Default)]
+    struct TestMotor {
+        speed: Option<f32>,
+    }
+
+    impl Motor for TestMotor {
+        fn set_speed(&mut self, speed: f32, _speed_range: (f32, f32), _deadband: f32) {
+            self.speed = Some(speed);
+        }
+    }
+
+    // Ticks on every wait while ticking is set
+    #[derive(Default)]
+    struct TestClock {
+        period_ms: u32,
+        ticking: bool,
+    }
+
+    impl Clock for TestClock {
+        fn start(&mut self, period_ms: u32) {
+            self.period_ms = period_ms;
+        }
+
+        fn wait(&mut self) -> bool {
+            self.ticking
+        }
+    }
+
+    struct TestConfigStorage {
+        config: Config,
+        writes: u32,
+    }
+
+    impl ConfigStorage for TestConfigStorage {
+        fn read(&self) -> Config {
+            self.config
+        }
+
+        fn write(&mut self, config: &Config) {
+            self.config = *config;
+            self.writes += 1;
+        }
+    }
+
+    type TestController = Controller<TestEncoder, TestMotor, TestClock, TestConfigStorage>;
+
+    fn controller(config: &Config) -> TestController {
+        Controller::new(
+            TestEncoder::default(),
+            TestMotor::default(),
+            TestClock::default(),
+            TestConfigStorage {
+                config: *config,
+                writes: 0,
+            },
+            config,
+        )
+    }
+
+    #[test]
+    fn clock_is_started_with_update_period() {
+        let controller = controller(&test_config());
+
+        assert_eq!(controller.clock.period_ms, 2);
+    }
+
+    #[test]
+    fn tick_drives_motor_from_encoder() {
+        let mut config = test_config();
+        config.spring_gain = 1.0;
+        config.spring_coefficient = 1.0;
+        config.spring_saturation = 1.0;
+        let mut racing_wheel = RacingWheel::new(config);
+        set_device_gain(&mut racing_wheel, 1.0);
+        let mut controller = controller(&config);
+        controller.encoder.angle = 90.0;
+
+        // Nothing happens between ticks
+        assert!(!controller.update(&mut racing_wheel, [false; 8]));
+        assert_eq!(controller.motor.speed, None);
+
+        // The spring pushes the wheel, a quarter turn away from the center, with half the force
+        controller.clock.ticking = true;
+        assert!(controller.update(&mut racing_wheel, [false; 8]));
+        assert!((controller.motor.speed.unwrap() - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn write_config_stores_config() {
+        let mut config = test_config();
+        let mut racing_wheel = RacingWheel::new(config);
+        config.gain = 0.5;
+        let mut controller = controller(&config);
+
+        controller.update(&mut racing_wheel, [false; 8]);
+        assert_eq!(controller.config_storage.writes, 0);
+
+        send_wheel_device_control(&mut racing_wheel, WheelDeviceControl::WriteConfig);
+        controller.update(&mut racing_wheel, [false; 8]);
+        assert_eq!(controller.config_storage.writes, 1);
+        assert_eq!(controller.config_storage.read().gain, 1.0);
+    }
+
+    #[test]
+    fn reset_rotation_resets_encoder() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        let mut controller = controller(&test_config());
+        controller.encoder.angle = 45.0;
+
+        send_wheel_device_control(&mut racing_wheel, WheelDeviceControl::ResetRotation);
+        controller.update(&mut racing_wheel, [false; 8]);
+
+        assert_eq!(controller.encoder.resets, 1);
+        assert_eq!(controller.encoder.angle, 0.0);
+    }
+}
@@ -0,0 +1,30 @@
+// The hardware the racing wheel needs, implemented by the firmware for its peripherals so that
+// the racing wheel itself does not depend on a specific microcontroller.
+use config::config::Config;
+
+pub trait Encoder {
+    // Steering angle in degrees from the center
+    fn angle(&mut self) -> f32;
+
+    // Makes the current position the center
+    fn reset(&mut self);
+}
+
+pub trait Motor {
+    // Drives the motor with a speed from -1 to 1, scaled into the speed range. Speeds within the
+    // deadband turn the motor off.
+    fn set_speed(&mut self, speed: f32, speed_range: (f32, f32), deadband: f32);
+}
+
+pub trait Clock {
+    // Starts a periodic timer with the update period
+    fn start(&mut self, period_ms: u32);
+
+    // Returns true once every period, without blocking
+    fn wait(&mut self) -> bool;
+}
+
+pub trait ConfigStorage {
+    fn read(&self) -> Config;
+    fn write(&mut self, config: &Config);
+}
@@ -0,0 +1,6 @@
+#![no_std]
+
+pub mod controller;
+pub mod hardware;
+pub mod misc;
+pub mod racing_wheel;
@@ -0,0 +1,138 @@
+use core::slice::{Iter, IterMut};
+
+pub struct FixedSet<T, const N: usize> {
+    array: [T; N],
+    n: usize,
+}
+
+impl<T: Eq + PartialEq + Copy + Clone + Default, const N: usize> FixedSet<T, N> {
+    pub fn new() -> Self {
+        Self {
+            array: [T::default(); N],
+            n: 0,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn insert(&mut self, elem: T) -> bool {
+        if self.n >= N || self.iter().any(|e| *e == elem) {
+            return false;
+        }
+        self.array[self.n] = elem;
+        self.n += 1;
+        true
+    }
+
+    pub fn remove(&mut self, v: T) -> bool {
+        let n_prev = self.n;
+
+        self.n = 0;
+        for i in 0..n_prev {
+            if self.array[i] != v {
+                self.array[self.n] = self.array[i];
+                self.n += 1;
+            }
+        }
+
+        self.n != n_prev
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.array[0..self.n].iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.array[0..self.n].iter_mut()
+    }
+}
+
+impl<T: Eq + PartialEq + Copy + Clone + Default, const N: usize> Default for FixedSet<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Helper functions
+pub fn bitflags(flags: &[bool]) -> u8 {
+    flags
+        .iter()
+        .enumerate()
+        .fold(0, |b, (i, flag)| b | (*flag as u8) << i)
+}
+
+pub fn bitflag(flags: u8, i: u8) -> bool {
+    (flags & (1 << i)) != 0
+}
+
+pub fn bits(byte: u8, start: u8, n_bits: u8) -> u8 {
+    (byte << i32::max(0_i32, 8_i32 - start as i32 - n_bits as i32)) >> (8 - n_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_set_holds_unique_elements_up_to_capacity() {
+        let mut set: FixedSet<u8, 3> = FixedSet::new();
+
+        assert!(set.insert(1));
+        assert!(!set.insert(1));
+        assert!(set.insert(2));
+        assert!(set.insert(3));
+        assert!(!set.insert(4));
+        assert_eq!(set.size(), 3);
+    }
+
+    #[test]
+    fn fixed_set_remove_keeps_order() {
+        let mut set: FixedSet<u8, 4> = FixedSet::new();
+        for elem in 1..=4 {
+            set.insert(elem);
+        }
+
+        assert!(set.remove(2));
+        assert!(!set.remove(2));
+        let mut elems = set.iter();
+        assert_eq!(elems.next(), Some(&1));
+        assert_eq!(elems.next(), Some(&3));
+        assert_eq!(elems.next(), Some(&4));
+        assert_eq!(elems.next(), None);
+        assert!(set.insert(2));
+    }
+
+    #[test]
+    fn fixed_set_reinserts_removed_last_element() {
+        let mut set: FixedSet<u8, 4> = FixedSet::new();
+        set.insert(1);
+        set.insert(2);
+
+        // The removed element is left past the end of the set, and must not block the insert
+        set.remove(2);
+        assert!(set.insert(2));
+        set.remove(1);
+        set.remove(2);
+        assert!(set.insert(2));
+        assert!(set.insert(1));
+        assert_eq!(set.size(), 2);
+    }
+
+    #[test]
+    fn fixed_set_accepts_default_value() {
+        let mut set: FixedSet<u8, 2> = FixedSet::new();
+
+        assert!(set.insert(0));
+        assert!(!set.insert(0));
+    }
+
+    #[test]
+    fn bit_helpers() {
+        assert_eq!(bitflags(&[true, false, true]), 0b101);
+        assert!(bitflag(0b100, 2));
+        assert!(!bitflag(0b100, 1));
+        assert_eq!(bits(0b1101_0110, 4, 2), 0b01);
+    }
+}
@@ -0,0 +1,1516 @@
+mod descriptor;
+mod expo_curve;
+mod force_sample_stream;
+mod hid_reports;
+mod racing_wheel_hid;
+mod ram_pool;
+
+use crate::misc::FixedSet;
+use config::config::{Config, TORQUE_CURVE_SIZE};
+use expo_curve::ExpoCurve;
+use force_feedback::{
+    effect::{create_damper_effect, create_spring_effect, Effect},
+    filter::{FilterConfig, OutputFilter},
+    force::{self, Force},
+    oscillation::{OscillationConfig, OscillationDetector},
+    reconstruction::ForceReconstruction,
+    render_state::RenderState,
+    renderer::{EffectRenderer, SteeringState, TypedEffect},
+    reports::*,
+};
+use force_sample_stream::{is_streamed, ForceSampleStream};
+use ram_pool::RAMPool;
+
+const CUSTOM_DATA_BUFFER_SIZE: usize = 4096;
+const MAX_EFFECTS: usize = 16;
+const MAX_SIMULTANEOUS_EFFECTS: usize = 8;
+const FORCE_SAMPLE_BUFFER_SIZE: usize = 16;
+const INFINITE_LOOP_COUNT: u8 = 255;
+const END_STOP_SATURATION: f32 = 10.0;
+const END_STOP_BUMP_DURATION_MS: u32 = 40;
+
+// With a host managed pool the host decides where effect parameters are stored, and parameter
+// blocks can be shared between effects
+const HOST_MANAGED_POOL: bool = cfg!(feature = "host-managed-pool");
+const MAX_PARAMETER_BLOCKS: usize = if HOST_MANAGED_POOL { 40 } else { 0 };
+
+pub struct RacingWheel {
+    ram_pool: RAMPool<MAX_EFFECTS, MAX_PARAMETER_BLOCKS, CUSTOM_DATA_BUFFER_SIZE>,
+    next_effect: Option<CreateNewEffect>,
+    running_effects: FixedSet<RunningEffect, MAX_SIMULTANEOUS_EFFECTS>,
+    force_sample_stream: ForceSampleStream<FORCE_SAMPLE_BUFFER_SIZE>,
+    output_filter: OutputFilter,
+    config_effects: ConfigEffects,
+    force_reconstruction: [ForceReconstruction; MAX_EFFECTS],
+    device_gain: f32,
+    racing_wheel_report: RacingWheelState,
+    pid_state_report: PIDState,
+    // Effect whose start was refused or that was evicted, it is reported to the host as not
+    // playing until the PID state report has been sent.
+    stopped_effect: Option<u8>,
+    buttons_prev: [bool; 8],
+    trigger_repeat_time: [u32; MAX_EFFECTS],
+    steering_prev: f32,
+    steering_velocity: f32,
+    steering_vel_prev: f32,
+    steering_acceleration: f32,
+    steering_acc_prev: f32,
+    end_stop_bump_time: Option<u32>,
+    oscillation_detector: OscillationDetector,
+    idle_time_ms: u32,
+    idle_level: f32,
+    config: Config,
+    write_config_event: bool,
+    reboot_device_event: bool,
+    reset_steering_event: bool,
+    pid_state_event: bool,
+}
+
+impl RacingWheel {
+    pub fn new(config: Config) -> Self {
+        RacingWheel {
+            ram_pool: RAMPool::new(),
+            next_effect: None,
+            running_effects: FixedSet::new(),
+            force_sample_stream: ForceSampleStream::new(),
+            output_filter: create_output_filter(&config),
+            config_effects: ConfigEffects::new(&config),
+            force_reconstruction: [ForceReconstruction::new(); MAX_EFFECTS],
+            device_gain: 0.0,
+            racing_wheel_report: RacingWheelState::default(),
+            pid_state_report: PIDState::default(),
+            stopped_effect: None,
+            buttons_prev: [false; 8],
+            trigger_repeat_time: [0; MAX_EFFECTS],
+            steering_prev: 0.0,
+            steering_velocity: 0.0,
+            steering_vel_prev: 0.0,
+            steering_acceleration: 0.0,
+            steering_acc_prev: 0.0,
+            end_stop_bump_time: None,
+            oscillation_detector: OscillationDetector::new(),
+            // The wheel is idle until the host creates an effect
+            idle_time_ms: u32::MAX,
+            idle_level: 1.0,
+            config,
+            write_config_event: false,
+            reboot_device_event: false,
+            reset_steering_event: false,
+            pid_state_event: false,
+        }
+    }
+
+    // Steering angle (degrees)
+    pub fn set_steering(&mut self, steering: f32) {
+        self.racing_wheel_report.steering = steering * 2.0 / (self.config.max_rotation as f32);
+    }
+
+    pub fn set_buttons(&mut self, buttons: [bool; 8]) {
+        self.racing_wheel_report.buttons = buttons;
+    }
+
+    pub fn get_config(&self) -> Config {
+        self.config
+    }
+
+    // The output filter and config effects are rebuilt, as they are calculated from the config
+    fn set_config(&mut self, config: Config) {
+        self.config = config;
+        self.output_filter = create_output_filter(&config);
+        self.config_effects = ConfigEffects::new(&config);
+    }
+
+    pub fn write_config_event(&mut self) -> bool {
+        let write_config = self.write_config_event;
+        self.write_config_event = false;
+        write_config
+    }
+
+    pub fn reboot_device_event(&mut self) -> bool {
+        let reboot_device = self.reboot_device_event;
+        self.reboot_device_event = false;
+        reboot_device
+    }
+
+    pub fn reset_steering_event(&mut self) -> bool {
+        if self.reset_steering_event {
+            self.reset_steering_event = false;
+
+            self.racing_wheel_report.steering = 0.0;
+            self.steering_prev = 0.0;
+            self.steering_velocity = 0.0;
+            self.steering_vel_prev = 0.0;
+            self.steering_acceleration = 0.0;
+            self.steering_acc_prev = 0.0;
+
+            return true;
+        }
+
+        false
+    }
+
+    pub fn pid_state_event(&mut self) -> bool {
+        let pid_state = self.pid_state_event;
+        self.pid_state_event = false;
+        pid_state
+    }
+
+    pub fn get_force_feedback(&self) -> f32 {
+        self.racing_wheel_report.ffb
+    }
+
+    pub fn advance(&mut self, delta_time_ms: u32) {
+        self.steering_velocity = (self.racing_wheel_report.steering - self.steering_prev)
+            * (1000.0 / delta_time_ms as f32)
+            * (1.0 - self.config.derivative_smoothing)
+            + self.steering_vel_prev * self.config.derivative_smoothing;
+
+        self.steering_acceleration = (self.steering_velocity - self.steering_vel_prev)
+            * (1000.0 / delta_time_ms as f32)
+            * (1.0 - self.config.acceleration_smoothing)
+            + self.steering_acc_prev * self.config.acceleration_smoothing;
+
+        self.steering_prev = self.racing_wheel_report.steering;
+        self.steering_vel_prev = self.steering_velocity;
+        self.steering_acc_prev = self.steering_acceleration;
+
+        // Effect timers are frozen and no torque is output while the device is paused
+        if self.pid_state_report.device_paused {
+            self.racing_wheel_report.ffb = 0.0;
+            self.update_pid_state();
+            return;
+        }
+
+        self.trigger_effects(delta_time_ms);
+
+        let mut still_running = FixedSet::new();
+        for running_effect in self.running_effects.iter_mut() {
+            running_effect.time += delta_time_ms;
+
+            let mut keep = true;
+            if let Some(effect) = self.ram_pool.get_effect(running_effect.index) {
+                if let Some(effect_report) = effect.effect_report {
+                    if let Some(duration) = effect_report.duration {
+                        let end_time = effect_report.start_delay as u32 + duration as u32;
+                        while running_effect.time >= end_time && running_effect.loop_count > 1 {
+                            running_effect.time -= duration as u32;
+                            if running_effect.loop_count != INFINITE_LOOP_COUNT {
+                                running_effect.loop_count -= 1;
+                            }
+                        }
+                        keep = keep && end_time > running_effect.time;
+                    }
+                }
+            }
+
+            if keep {
+                still_running.insert(*running_effect);
+            }
+        }
+
+        self.running_effects = still_running;
+        self.update_pid_state();
+
+        let oscillating = self.oscillation_detector.update(
+            self.racing_wheel_report.steering,
+            self.steering_velocity,
+            delta_time_ms,
+            &OscillationConfig {
+                window_ms: self.config.oscillation_window_ms as u32,
+                min_crossings: self.config.oscillation_min_crossings,
+                min_velocity: self.config.oscillation_min_velocity,
+            },
+        );
+        let suppression = self.update_oscillation_suppression(oscillating, delta_time_ms);
+        let idle_level = self.update_idle_level(delta_time_ms);
+
+        // The rendered forces are added up before they are converted to floating point
+        let mut rendered = force::ZERO;
+        let steering = SteeringState::new(
+            self.racing_wheel_report.steering,
+            self.steering_velocity,
+            self.steering_acceleration,
+        );
+
+        // Apply PID effects
+        for running_effect in self.running_effects.iter() {
+            let effect = self.ram_pool.get_effect(running_effect.index);
+            let t = effect.and_then(|effect| running_effect.effect_time(effect));
+
+            if let (Ok(effect), Some(t)) = (self.ram_pool.render_state(running_effect.index), t) {
+                let custom_data = self.ram_pool.custom_data(running_effect.index);
+                let gain = self.effect_type_gain(effect.effect());
+                let force = self.force_reconstruction[running_effect.index as usize - 1].apply(
+                    effect.effect(),
+                    effect.render(t, &steering, custom_data),
+                    delta_time_ms,
+                    self.config.reconstruction_latency_ms as u32,
+                );
+                rendered += force::mul(force, gain);
+            }
+        }
+
+        // Apply spring and damper effects
+        let spring = self.config_effects.spring.render(0, &steering, &[]);
+        let damper = self.config_effects.damper.render(0, &steering, &[]);
+        rendered += force::mul(spring + damper, force::from_f32(idle_level));
+
+        // Apply virtual end stop spring
+        rendered += self
+            .config_effects
+            .end_stop_spring
+            .render(0, &steering, &[]);
+
+        let mut total = force::to_f32(rendered);
+
+        // Apply streamed force samples
+        let streamed_effect = self
+            .running_effects
+            .iter()
+            .filter(|running_effect| {
+                self.ram_pool
+                    .get_effect(running_effect.index)
+                    .and_then(|effect| running_effect.effect_time(effect))
+                    .is_some()
+            })
+            .filter_map(|running_effect| self.ram_pool.render_state(running_effect.index).ok())
+            .find(|render_state| is_streamed(render_state.effect()))
+            .map(|render_state| *render_state.effect().effect_report());
+
+        if let Some(effect) = streamed_effect {
+            let sample_period = effect.sample_period.unwrap_or(1) as u32;
+            let force = self
+                .force_sample_stream
+                .advance(delta_time_ms, sample_period);
+            total += force * effect.gain * self.config.custom_gain;
+        } else {
+            // Samples are only kept while a streamed effect plays, so that a stale stream is not
+            // played back when the next streamed effect is started
+            self.force_sample_stream.clear();
+        }
+
+        // Extra damping when the wheel oscillates while idle
+        if oscillating {
+            total += idle_level * self.steering_velocity * self.config.idle_oscillation_damping;
+        }
+
+        // Apply virtual end stop damper and bump
+        total += self.end_stop_force(delta_time_ms);
+
+        // Reduce the gain and add damping until the wheel stops oscillating
+        total *= 1.0 - suppression * f32::clamp(self.config.oscillation_gain_reduction, 0.0, 1.0);
+        total += suppression * self.steering_velocity * self.config.oscillation_damping;
+
+        if !self.pid_state_report.actuators_enabled {
+            self.racing_wheel_report.ffb = 0.0;
+            return;
+        }
+
+        let ffb = self
+            .output_filter
+            .apply(total * self.device_gain * self.config.gain);
+        let points = usize::min(self.config.torque_curve_points as usize, TORQUE_CURVE_SIZE);
+        // Without torque there is no output, the first curve point is the output just above zero
+        let output = if ffb == 0.0 {
+            0.0
+        } else if points >= 2 {
+            linearize_torque(&self.config.torque_curve[..points], f32::abs(ffb))
+        } else {
+            self.config_effects.expo_curve.apply(f32::abs(ffb))
+        };
+        self.racing_wheel_report.ffb = f32::clamp(f32::signum(ffb) * output, -1.0, 1.0)
+    }
+
+    // Fades the config spring and damper in after no effects have been created or played for the
+    // idle timeout, and out again as soon as the host plays an effect.
+    fn update_idle_level(&mut self, delta_time_ms: u32) -> f32 {
+        if self.config.idle_timeout_s == 0 {
+            self.idle_level = 1.0;
+            return self.idle_level;
+        }
+
+        if self.pid_state_report.effect_playing {
+            self.idle_time_ms = 0;
+        } else {
+            self.idle_time_ms = self.idle_time_ms.saturating_add(delta_time_ms);
+        }
+
+        let idle = self.idle_time_ms >= self.config.idle_timeout_s as u32 * 1000;
+        let step = if self.config.idle_fade_ms > 0 {
+            delta_time_ms as f32 / self.config.idle_fade_ms as f32
+        } else {
+            1.0
+        };
+        self.idle_level = if idle {
+            f32::min(self.idle_level + step, 1.0)
+        } else {
+            f32::max(self.idle_level - step, 0.0)
+        };
+        self.idle_level
+    }
+
+    // Ramps the oscillation suppression in while the wheel oscillates, and out again after it
+    // has stopped. The level is reported to the host in the input report.
+    fn update_oscillation_suppression(&mut self, oscillating: bool, delta_time_ms: u32) -> f32 {
+        let step = if self.config.oscillation_ramp_ms > 0 {
+            delta_time_ms as f32 / self.config.oscillation_ramp_ms as f32
+        } else {
+            1.0
+        };
+        let suppression = self.racing_wheel_report.oscillation_suppression;
+        self.racing_wheel_report.oscillation_suppression = if oscillating {
+            f32::min(suppression + step, 1.0)
+        } else {
+            f32::max(suppression - step, 0.0)
+        };
+        self.racing_wheel_report.oscillation_suppression
+    }
+
+    // Gain from the config for the type of a game effect
+    fn effect_type_gain(&self, effect: &TypedEffect) -> Force {
+        let gains = &self.config_effects.effect_type_gains;
+        match effect {
+            TypedEffect::ConstantForce(_) => gains.constant,
+            TypedEffect::RampForce(_) => gains.ramp,
+            TypedEffect::Periodic(_) => gains.periodic,
+            TypedEffect::Condition(_) => gains.condition,
+            TypedEffect::CustomForce(_) => gains.custom,
+        }
+    }
+
+    // Damper beyond the end stop start, and a bump that fades out over END_STOP_BUMP_DURATION_MS
+    // when the end stop is reached. The end stop spring is rendered with the other effects.
+    fn end_stop_force(&mut self, delta_time_ms: u32) -> f32 {
+        let steering = self.racing_wheel_report.steering;
+        let start = self.config.end_stop_start;
+        let in_end_stop = f32::abs(steering) > start;
+
+        let damper = if in_end_stop {
+            f32::clamp(
+                self.steering_velocity * self.config.end_stop_damping,
+                -END_STOP_SATURATION,
+                END_STOP_SATURATION,
+            )
+        } else {
+            0.0
+        };
+
+        self.end_stop_bump_time = match self.end_stop_bump_time {
+            _ if !in_end_stop => None,
+            None => Some(0),
+            Some(time) => Some(time + delta_time_ms),
+        };
+        let bump = match self.end_stop_bump_time {
+            Some(time) if time < END_STOP_BUMP_DURATION_MS => {
+                let fade = 1.0 - time as f32 / END_STOP_BUMP_DURATION_MS as f32;
+                f32::signum(steering) * self.config.end_stop_bump * fade
+            }
+            _ => 0.0,
+        };
+
+        damper + bump
+    }
+
+    // Updates the effect playback status in the PID state report from the running effects, and
+    // raises the PID state event if it changed so that the report can be sent immediately. An
+    // effect that was refused or evicted is reported first, so that the host sees it stopped.
+    fn update_pid_state(&mut self) {
+        let playing_effect = self.running_effects.iter().find(|running_effect| {
+            self.ram_pool
+                .get_effect(running_effect.index)
+                .and_then(|effect| running_effect.effect_time(effect))
+                .is_some()
+        });
+
+        let (effect_playing, effect_block_index) = match (self.stopped_effect, playing_effect) {
+            (Some(stopped_effect), _) => (false, stopped_effect),
+            _ if self.pid_state_report.device_paused => {
+                (false, self.pid_state_report.effect_block_index)
+            }
+            (None, Some(running_effect)) => (true, running_effect.index),
+            (None, None) => (false, self.pid_state_report.effect_block_index),
+        };
+
+        if effect_playing != self.pid_state_report.effect_playing
+            || effect_block_index != self.pid_state_report.effect_block_index
+        {
+            self.pid_state_report.effect_playing = effect_playing;
+            self.pid_state_report.effect_block_index = effect_block_index;
+            self.pid_state_event = true;
+        }
+    }
+
+    // Starts effects that have a trigger button when the button is pressed, and restarts them
+    // every trigger repeat interval while the button is held.
+    fn trigger_effects(&mut self, delta_time_ms: u32) {
+        let buttons = self.racing_wheel_report.buttons;
+        let mut triggered: FixedSet<RunningEffect, MAX_EFFECTS> = FixedSet::new();
+
+        for (index, effect) in self.ram_pool.effects() {
+            let effect_report = match effect.effect_report {
+                Some(effect_report) => effect_report,
+                None => continue,
+            };
+
+            let button = effect_report.trigger_button as usize;
+            if button == 0 || button > buttons.len() {
+                continue;
+            }
+
+            let pressed = buttons[button - 1];
+            let was_pressed = self.buttons_prev[button - 1];
+            let repeat_interval = effect_report.trigger_repeat_interval as u32;
+            let repeat_time = &mut self.trigger_repeat_time[index as usize - 1];
+
+            let start = if pressed && !was_pressed {
+                *repeat_time = 0;
+                true
+            } else if pressed && repeat_interval > 0 {
+                *repeat_time += delta_time_ms;
+                if *repeat_time >= repeat_interval {
+                    *repeat_time -= repeat_interval;
+                    true
+                } else {
+                    false
+                }
+            } else {
+                false
+            };
+
+            if start {
+                triggered.insert(RunningEffect::new(index));
+            }
+        }
+
+        for running_effect in triggered.iter() {
+            self.start_effect(*running_effect);
+        }
+
+        self.buttons_prev = buttons;
+    }
+
+    // The PID state report with a stopped effect was sent, so the playback status is reported
+    // from the running effects again.
+    fn pid_state_sent(&mut self) {
+        self.stopped_effect = None;
+    }
+
+    // Reports an effect that is not playing although it was started, see `stopped_effect`
+    fn report_stopped_effect(&mut self, index: u8) {
+        self.stopped_effect = Some(index);
+        self.pid_state_event = true;
+    }
+
+    // Starts (or restarts) an effect. If the simultaneous effect limit is reached, the oldest of
+    // the lowest priority running effects is evicted to make room, unless all of them outrank the
+    // new effect, in which case false is returned and the effect is not started. Effects that are
+    // not complete are not started either. Refused and evicted effects are reported to the host
+    // in the PID state report.
+    fn start_effect(&mut self, running_effect: RunningEffect) -> bool {
+        if self.ram_pool.render_state(running_effect.index).is_err() {
+            self.report_stopped_effect(running_effect.index);
+            return false;
+        }
+
+        if self.stopped_effect == Some(running_effect.index) {
+            self.stopped_effect = None;
+        }
+
+        self.force_reconstruction[running_effect.index as usize - 1] = ForceReconstruction::new();
+        self.running_effects.remove(running_effect);
+        if self.running_effects.insert(running_effect) {
+            return true;
+        }
+
+        let priority = self.effect_priority(running_effect.index);
+        let evicted = self
+            .running_effects
+            .iter()
+            .min_by_key(|running_effect| self.effect_priority(running_effect.index))
+            .filter(|running_effect| self.effect_priority(running_effect.index) <= priority)
+            .copied();
+
+        match evicted {
+            Some(evicted) => {
+                self.running_effects.remove(evicted);
+                self.report_stopped_effect(evicted.index);
+                self.running_effects.insert(running_effect)
+            }
+            None => {
+                self.report_stopped_effect(running_effect.index);
+                false
+            }
+        }
+    }
+
+    // Condition effects are usually essential for steering feel, while periodic effects are
+    // mostly rumble, so they are the first to be evicted.
+    fn effect_priority(&self, index: u8) -> u8 {
+        use EffectType::*;
+
+        let effect_type = self
+            .ram_pool
+            .get_effect(index)
+            .and_then(|effect| effect.effect_report)
+            .map(|effect_report| effect_report.effect_type);
+
+        match effect_type {
+            Some(Spring | Damper | Inertia | Friction) => 2,
+            Some(Square | Sine | Triangle | SawtoothUp | SawtoothDown) => 0,
+            _ => 1,
+        }
+    }
+}
+
+// The filters run once per update, so the update frequency is their sample rate
+fn create_output_filter(config: &Config) -> OutputFilter {
+    OutputFilter::new(&FilterConfig {
+        low_pass_cutoff_hz: config.low_pass_cutoff_hz,
+        notch_frequency_hz: config.notch_frequency_hz,
+        notch_q: config.notch_q,
+        slew_rate_limit: config.slew_rate_limit,
+        sample_rate_hz: config.update_frequency_hz as f32,
+    })
+}
+
+// Effects of the device itself, built when the config is set so that rendering them every update
+// only uses precomputed values. The spring and damper are rendered at full level and scaled by
+// the idle level. The expo is sampled into a curve, as calculating a power every update is too
+// slow without a floating point unit.
+struct ConfigEffects {
+    spring: RenderState,
+    damper: RenderState,
+    end_stop_spring: RenderState,
+    effect_type_gains: EffectTypeGains,
+    expo_curve: ExpoCurve,
+}
+
+// The config gains of the game effect types, in the number type effects are rendered with
+struct EffectTypeGains {
+    constant: Force,
+    ramp: Force,
+    periodic: Force,
+    condition: Force,
+    custom: Force,
+}
+
+impl ConfigEffects {
+    fn new(config: &Config) -> Self {
+        let spring = create_spring_effect(
+            config.spring_gain,
+            None,
+            0.0,
+            config.spring_coefficient,
+            config.spring_coefficient,
+            config.spring_saturation,
+            config.spring_saturation,
+            config.spring_deadband,
+        );
+        let damper = create_damper_effect(
+            config.damper_gain,
+            None,
+            0.0,
+            config.damper_coefficient,
+            config.damper_coefficient,
+            config.damper_saturation,
+            config.damper_saturation,
+            config.damper_deadband,
+        );
+        let end_stop_spring = create_spring_effect(
+            1.0,
+            None,
+            0.0,
+            config.end_stop_stiffness,
+            config.end_stop_stiffness,
+            END_STOP_SATURATION,
+            END_STOP_SATURATION,
+            config.end_stop_start,
+        );
+
+        Self {
+            spring: config_effect(&spring),
+            damper: config_effect(&damper),
+            end_stop_spring: config_effect(&end_stop_spring),
+            effect_type_gains: EffectTypeGains {
+                constant: force::from_f32(config.constant_gain),
+                ramp: force::from_f32(config.ramp_gain),
+                periodic: force::from_f32(config.periodic_gain),
+                condition: force::from_f32(config.condition_gain),
+                custom: force::from_f32(config.custom_gain),
+            },
+            expo_curve: ExpoCurve::new(config.expo),
+        }
+    }
+}
+
+// The config effects are complete condition effects, so they are always valid
+fn config_effect(effect: &Effect) -> RenderState {
+    RenderState::new(TypedEffect::try_from(effect).unwrap())
+}
+
+// Interpolates the motor output for a torque (0 to 1) from a curve with points at evenly spaced
+// torques, the curve needs at least two points.
+fn linearize_torque(curve: &[f32], torque: f32) -> f32 {
+    let position = f32::clamp(torque, 0.0, 1.0) * (curve.len() - 1) as f32;
+    let index = usize::min(position as usize, curve.len() - 2);
+    let fraction = position - index as f32;
+    curve[index] + (curve[index + 1] - curve[index]) * fraction
+}
+
+#[derive(Copy, Clone, Eq, Default)]
+struct RunningEffect {
+    index: u8,
+    time: u32,
+    loop_count: u8,
+}
+
+impl PartialEq for RunningEffect {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl RunningEffect {
+    fn new(index: u8) -> Self {
+        Self::looped(index, 1)
+    }
+
+    // Plays the effect loop_count times, where INFINITE_LOOP_COUNT loops until it is stopped.
+    fn looped(index: u8, loop_count: u8) -> Self {
+        Self {
+            index,
+            time: 0,
+            loop_count: u8::max(loop_count, 1),
+        }
+    }
+
+    // Time since the effect started playing, or None while it is waiting for its start delay.
+    fn effect_time(&self, effect: &Effect) -> Option<u32> {
+        let start_delay = effect.effect_report.map(|e| e.start_delay).unwrap_or(0);
+        self.time.checked_sub(start_delay as u32)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use config::control::WheelDeviceControl;
+    use core::f32::consts::FRAC_1_SQRT_2;
+    use force_feedback::renderer::{
+        ConditionEffect, ConstantForceEffect, CustomForceEffect, PeriodicEffect, RampForceEffect,
+    };
+    use hid_reports::Report;
+    use usb_hid_device::hid_device::{
+        HIDDeviceType, HIDReport, HIDReportRAM, ReportID, ReportType,
+    };
+
+    const TICK_MS: u32 = 2;
+
+    // Config without the config spring, damper and end stop, and with a linear output
+    pub(crate) fn test_config() -> Config {
+        let mut config = Config::from_bytes(&[0; Config::REPORT_SIZE - 1]).unwrap();
+        config.gain = 1.0;
+        config.expo = 1.0;
+        config.max_rotation = 360;
+        config.motor_max = 1.0;
+        config.update_frequency_hz = 500;
+        config.constant_gain = 1.0;
+        config
+    }
+
+    // Sends an output report with an effect block index followed by the report data
+    fn send(
+        racing_wheel: &mut RacingWheel,
+        report_id: ReportID,
+        effect_block_index: u8,
+        data: &[u8],
+    ) -> Option<bool> {
+        let mut bytes = [0; 32];
+        bytes[0] = report_id.1;
+        bytes[1] = effect_block_index;
+        bytes[2..data.len() + 2].copy_from_slice(data);
+        racing_wheel
+            .report_request_out(report_id, &bytes[..data.len() + 2])
+            .unwrap()
+    }
+
+    // Sends a parameter report of an effect. In host managed mode the report is addressed to the
+    // parameter block with the same index as the effect.
+    fn send_parameter(
+        racing_wheel: &mut RacingWheel,
+        report_id: ReportID,
+        effect_block_index: u8,
+        data: &[u8],
+    ) -> Option<bool> {
+        if !HOST_MANAGED_POOL {
+            return send(racing_wheel, report_id, effect_block_index, data);
+        }
+
+        let offset = parameter_block_offset(effect_block_index as usize - 1);
+        let mut bytes = [0; 32];
+        bytes[0] = report_id.1;
+        bytes[1..3].copy_from_slice(&offset.to_le_bytes());
+        bytes[3..data.len() + 3].copy_from_slice(data);
+        racing_wheel
+            .report_request_out(report_id, &bytes[..data.len() + 3])
+            .unwrap()
+    }
+
+    pub(crate) fn set_device_gain(racing_wheel: &mut RacingWheel, gain: f32) {
+        let gain = ((gain * 10_000.0) as i16).to_le_bytes();
+        racing_wheel
+            .report_request_out(Report::<DeviceGain>::ID, &[0x0D, gain[0], gain[1]])
+            .unwrap();
+    }
+
+    pub(crate) fn send_wheel_device_control(
+        racing_wheel: &mut RacingWheel,
+        control: WheelDeviceControl,
+    ) {
+        racing_wheel
+            .report_request_out(Report::<WheelDeviceControl>::ID, &[0x05, control as u8])
+            .unwrap();
+    }
+
+    fn parameter_block_offset(block: usize) -> u16 {
+        RAMPool::<MAX_EFFECTS, MAX_PARAMETER_BLOCKS, CUSTOM_DATA_BUFFER_SIZE>::parameter_block_offset(
+            block,
+        )
+    }
+
+    fn send_force_sample(racing_wheel: &mut RacingWheel, steering: i8) {
+        send(
+            racing_wheel,
+            Report::<DownloadForceSample>::ID,
+            steering as u8,
+            &[0],
+        );
+    }
+
+    // Creates a custom force effect without samples, which plays the streamed force samples
+    fn create_streamed_effect(racing_wheel: &mut RacingWheel, sample_period: u16) -> u8 {
+        send(
+            racing_wheel,
+            Report::<CreateNewEffect>::ID,
+            EffectType::CustomForceData as u8,
+            &[0, 0],
+        );
+        let index = racing_wheel.block_load().effect_block_index;
+
+        let set_effect = Report(SetEffect {
+            effect_block_index: index,
+            effect_type: EffectType::CustomForceData,
+            sample_period: Some(sample_period),
+            gain: 1.0,
+            axis_x_enable: true,
+            ..SetEffect::default()
+        });
+        send(
+            racing_wheel,
+            Report::<SetEffect>::ID,
+            index,
+            &set_effect.to_ram(),
+        );
+        index
+    }
+
+    fn create_constant_force(racing_wheel: &mut RacingWheel, magnitude: f32) -> u8 {
+        create_effect(racing_wheel, magnitude, SetEffect::default())
+    }
+
+    // Creates and sets a constant force effect the way a host does, with the timing and trigger
+    // of `set_effect`, returns its effect block index.
+    fn create_effect(racing_wheel: &mut RacingWheel, magnitude: f32, set_effect: SetEffect) -> u8 {
+        let constant_force = Report(SetConstantForce {
+            effect_block_index: 0,
+            magnitude,
+        });
+        create_typed_effect(
+            racing_wheel,
+            EffectType::ConstantForce,
+            Report::<SetConstantForce>::ID,
+            &constant_force.to_ram(),
+            set_effect,
+        )
+    }
+
+    // Creates a sine effect, which has a lower priority than constant forces
+    fn create_sine(racing_wheel: &mut RacingWheel, set_effect: SetEffect) -> u8 {
+        let periodic = Report(SetPeriodic {
+            effect_block_index: 0,
+            magnitude: 0.5,
+            offset: 0.0,
+            phase: 0,
+            period: 100,
+        });
+        create_typed_effect(
+            racing_wheel,
+            EffectType::Sine,
+            Report::<SetPeriodic>::ID,
+            &periodic.to_ram(),
+            set_effect,
+        )
+    }
+
+    // Creates and sets an effect with a single parameter report the way a host does, returns its
+    // effect block index. With a host managed pool every effect gets the parameter block with the
+    // same index.
+    fn create_typed_effect(
+        racing_wheel: &mut RacingWheel,
+        effect_type: EffectType,
+        parameter_report_id: ReportID,
+        parameter: &[u8],
+        set_effect: SetEffect,
+    ) -> u8 {
+        send(
+            racing_wheel,
+            Report::<CreateNewEffect>::ID,
+            effect_type as u8,
+            &[0, 0],
+        );
+        let block_load = racing_wheel.block_load();
+        assert!(matches!(
+            block_load.block_load_status,
+            BlockLoadStatus::Success
+        ));
+        let index = block_load.effect_block_index;
+
+        let set_effect = Report(SetEffect {
+            effect_block_index: index,
+            effect_type,
+            gain: 1.0,
+            axis_x_enable: true,
+            type_specific_block_offset_instance_1: parameter_block_offset(index as usize - 1),
+            ..set_effect
+        });
+        // Hosts download the parameter blocks before the effect that uses them
+        assert_eq!(
+            send_parameter(racing_wheel, parameter_report_id, index, parameter),
+            Some(true)
+        );
+        send(
+            racing_wheel,
+            Report::<SetEffect>::ID,
+            index,
+            &set_effect.to_ram(),
+        );
+        index
+    }
+
+    // Returns if the operation succeeded, a refused start is Some(false)
+    fn effect_operation(
+        racing_wheel: &mut RacingWheel,
+        index: u8,
+        operation: EffectOperation,
+    ) -> Option<bool> {
+        effect_operation_looped(racing_wheel, index, operation, 1)
+    }
+
+    fn effect_operation_looped(
+        racing_wheel: &mut RacingWheel,
+        index: u8,
+        operation: EffectOperation,
+        loop_count: u8,
+    ) -> Option<bool> {
+        send(
+            racing_wheel,
+            Report::<SetEffectOperation>::ID,
+            index,
+            &[operation as u8, loop_count],
+        )
+    }
+
+    // Starts as many constant forces as can play at once, returns their effect block indices
+    fn start_simultaneous_effects(
+        racing_wheel: &mut RacingWheel,
+    ) -> [u8; MAX_SIMULTANEOUS_EFFECTS] {
+        let mut indices = [0; MAX_SIMULTANEOUS_EFFECTS];
+        for index in indices.iter_mut() {
+            *index = create_constant_force(racing_wheel, 0.1);
+            let started = effect_operation(racing_wheel, *index, EffectOperation::EffectStart);
+            assert_eq!(started, Some(true));
+        }
+        racing_wheel.pid_state_event();
+        indices
+    }
+
+    fn is_running(racing_wheel: &RacingWheel, index: u8) -> bool {
+        racing_wheel
+            .running_effects
+            .iter()
+            .any(|running_effect| running_effect.index == index)
+    }
+
+    // Advances the racing wheel for a duration, returns if an effect was playing at the end
+    fn advance_for(racing_wheel: &mut RacingWheel, duration_ms: u32) -> bool {
+        for _ in 0..duration_ms / TICK_MS {
+            racing_wheel.advance(TICK_MS);
+        }
+        racing_wheel.pid_state_report.effect_playing
+    }
+
+    #[test]
+    fn block_load_without_new_effect_is_an_error() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+
+        assert!(matches!(
+            racing_wheel.block_load().block_load_status,
+            BlockLoadStatus::Error
+        ));
+    }
+
+    #[test]
+    fn started_effect_is_played() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        set_device_gain(&mut racing_wheel, 1.0);
+        let index = create_constant_force(&mut racing_wheel, 0.5);
+
+        racing_wheel.advance(TICK_MS);
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+        assert!(racing_wheel.pid_state_event());
+        assert!(racing_wheel.pid_state_report.effect_playing);
+        assert_eq!(racing_wheel.pid_state_report.effect_block_index, index);
+
+        racing_wheel.advance(TICK_MS);
+        assert!((racing_wheel.get_force_feedback() - 0.5).abs() < 1e-3);
+
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStop);
+        assert!(racing_wheel.pid_state_event());
+        assert!(!racing_wheel.pid_state_report.effect_playing);
+
+        racing_wheel.advance(TICK_MS);
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+    }
+
+    #[test]
+    fn force_samples_are_only_kept_while_streamed_effect_plays() {
+        let mut config = test_config();
+        config.custom_gain = 1.0;
+        let mut racing_wheel = RacingWheel::new(config);
+        set_device_gain(&mut racing_wheel, 1.0);
+
+        // Samples sent before the effect plays are dropped
+        send_force_sample(&mut racing_wheel, 127);
+        racing_wheel.advance(TICK_MS);
+        let index = create_streamed_effect(&mut racing_wheel, TICK_MS as u16);
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+        racing_wheel.advance(TICK_MS);
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+
+        send_force_sample(&mut racing_wheel, 64);
+        racing_wheel.advance(TICK_MS);
+        assert!((racing_wheel.get_force_feedback() - 64.0 / 127.0).abs() < 1e-3);
+
+        // The held sample is not played again after the effect is restarted
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStop);
+        racing_wheel.advance(TICK_MS);
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+        racing_wheel.advance(TICK_MS);
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+    }
+
+    #[test]
+    fn expo_shapes_output() {
+        let mut config = test_config();
+        config.expo = 0.5;
+        let mut racing_wheel = RacingWheel::new(config);
+        set_device_gain(&mut racing_wheel, 1.0);
+        let index = create_constant_force(&mut racing_wheel, -0.3);
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+
+        racing_wheel.advance(TICK_MS);
+        let expected = -f32::sqrt(0.3);
+        assert!((racing_wheel.get_force_feedback() - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn expo_is_accurate_for_small_torques() {
+        let mut config = test_config();
+        for expo in [0.5, 0.9] {
+            config.expo = expo;
+            for magnitude in [0.005, 0.01, 0.05] {
+                let expected = f32::powf(magnitude, expo);
+                let output = constant_force_output(config, magnitude);
+                assert!((output - expected).abs() < 0.01 * expected);
+            }
+        }
+    }
+
+    // Output for a constant force effect played for one tick
+    fn constant_force_output(config: Config, magnitude: f32) -> f32 {
+        let mut racing_wheel = RacingWheel::new(config);
+        set_device_gain(&mut racing_wheel, 1.0);
+        let index = create_constant_force(&mut racing_wheel, magnitude);
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+
+        racing_wheel.advance(TICK_MS);
+        racing_wheel.get_force_feedback()
+    }
+
+    #[test]
+    fn torque_curve_is_interpolated_and_clamped() {
+        let curve = [0.1, 0.5, 1.0];
+
+        assert_eq!(linearize_torque(&curve, 0.0), 0.1);
+        assert!((linearize_torque(&curve, 0.25) - 0.3).abs() < 1e-6);
+        assert!((linearize_torque(&curve, 0.75) - 0.75).abs() < 1e-6);
+        assert_eq!(linearize_torque(&curve, 1.0), 1.0);
+
+        // Torques outside of the curve are clamped to its ends
+        assert_eq!(linearize_torque(&curve, -0.5), 0.1);
+        assert_eq!(linearize_torque(&curve, 1.5), 1.0);
+    }
+
+    #[test]
+    fn torque_curve_with_less_than_two_points_uses_expo() {
+        let mut config = test_config();
+        config.expo = 0.5;
+        config.torque_curve = [0.1, 0.9, 0.9, 0.9, 0.9, 0.9, 0.9, 0.9];
+
+        config.torque_curve_points = 1;
+        let expected = -f32::sqrt(0.3);
+        assert!((constant_force_output(config, -0.3) - expected).abs() < 1e-3);
+
+        config.torque_curve_points = 2;
+        let expected = -(0.1 + 0.8 * 0.3);
+        assert!((constant_force_output(config, -0.3) - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn torque_curve_outputs_nothing_without_torque() {
+        let mut config = test_config();
+        config.torque_curve[0] = 0.1;
+        config.torque_curve[1] = 1.0;
+        config.torque_curve_points = 2;
+        let mut racing_wheel = RacingWheel::new(config);
+        set_device_gain(&mut racing_wheel, 1.0);
+
+        racing_wheel.advance(TICK_MS);
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+
+        // The smallest torque already gets the first point of the curve
+        assert!((constant_force_output(config, -0.001) + 0.1).abs() < 1e-2);
+    }
+
+    #[test]
+    fn effect_type_gain_matches_config() {
+        let mut config = test_config();
+        config.constant_gain = 0.1;
+        config.ramp_gain = 0.2;
+        config.periodic_gain = 0.3;
+        config.condition_gain = 0.4;
+        config.custom_gain = 0.5;
+        let racing_wheel = RacingWheel::new(config);
+
+        let effect = SetEffect::default();
+        let effects = [
+            TypedEffect::ConstantForce(ConstantForceEffect {
+                effect,
+                constant_force: SetConstantForce {
+                    effect_block_index: 1,
+                    magnitude: 1.0,
+                },
+                envelope: None,
+            }),
+            TypedEffect::RampForce(RampForceEffect {
+                effect,
+                ramp_force: SetRampForce {
+                    effect_block_index: 1,
+                    ramp_start: 0.0,
+                    ramp_end: 1.0,
+                },
+                envelope: None,
+            }),
+            TypedEffect::Periodic(PeriodicEffect {
+                effect,
+                periodic: SetPeriodic {
+                    effect_block_index: 1,
+                    magnitude: 1.0,
+                    offset: 0.0,
+                    phase: 0,
+                    period: 100,
+                },
+                envelope: None,
+            }),
+            TypedEffect::Condition(ConditionEffect {
+                effect,
+                condition: SetCondition::default(),
+            }),
+            TypedEffect::CustomForce(CustomForceEffect {
+                effect,
+                custom_force: None,
+                envelope: None,
+            }),
+        ];
+
+        for (effect, expected) in effects.iter().zip([0.1, 0.2, 0.3, 0.4, 0.5]) {
+            let gain = force::to_f32(racing_wheel.effect_type_gain(effect));
+            assert!((gain - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn end_stop_damps_only_past_start() {
+        let mut config = test_config();
+        config.end_stop_start = 0.9;
+        config.end_stop_damping = 2.0;
+        let mut racing_wheel = RacingWheel::new(config);
+        racing_wheel.steering_velocity = 0.1;
+
+        racing_wheel.racing_wheel_report.steering = 0.85;
+        assert_eq!(racing_wheel.end_stop_force(TICK_MS), 0.0);
+
+        racing_wheel.racing_wheel_report.steering = 0.95;
+        assert!((racing_wheel.end_stop_force(TICK_MS) - 0.2).abs() < 1e-6);
+
+        racing_wheel.racing_wheel_report.steering = -0.95;
+        assert!((racing_wheel.end_stop_force(TICK_MS) - 0.2).abs() < 1e-6);
+
+        // The damping is saturated
+        racing_wheel.steering_velocity = -100.0;
+        assert_eq!(racing_wheel.end_stop_force(TICK_MS), -END_STOP_SATURATION);
+    }
+
+    #[test]
+    fn end_stop_bump_fades_out_after_reaching_end_stop() {
+        let mut config = test_config();
+        config.end_stop_start = 0.9;
+        config.end_stop_bump = 0.5;
+        let mut racing_wheel = RacingWheel::new(config);
+
+        racing_wheel.racing_wheel_report.steering = 0.85;
+        assert_eq!(racing_wheel.end_stop_force(TICK_MS), 0.0);
+
+        // The bump starts at full force and fades out over END_STOP_BUMP_DURATION_MS
+        racing_wheel.racing_wheel_report.steering = 0.95;
+        assert_eq!(racing_wheel.end_stop_force(TICK_MS), 0.5);
+        let expected = 0.5 * (1.0 - TICK_MS as f32 / END_STOP_BUMP_DURATION_MS as f32);
+        assert!((racing_wheel.end_stop_force(TICK_MS) - expected).abs() < 1e-6);
+        for _ in 2..END_STOP_BUMP_DURATION_MS / TICK_MS {
+            assert!(racing_wheel.end_stop_force(TICK_MS) > 0.0);
+        }
+        assert_eq!(racing_wheel.end_stop_force(TICK_MS), 0.0);
+
+        // Leaving the end stop rearms the bump, which pushes back towards the center
+        racing_wheel.racing_wheel_report.steering = 0.0;
+        assert_eq!(racing_wheel.end_stop_force(TICK_MS), 0.0);
+        racing_wheel.racing_wheel_report.steering = -0.95;
+        assert_eq!(racing_wheel.end_stop_force(TICK_MS), -0.5);
+    }
+
+    #[test]
+    fn idle_level_fades_in_after_timeout() {
+        let mut config = test_config();
+        config.idle_timeout_s = 1;
+        config.idle_fade_ms = 100;
+        let mut racing_wheel = RacingWheel::new(config);
+
+        // The wheel is idle until the host creates an effect
+        assert_eq!(racing_wheel.update_idle_level(TICK_MS), 1.0);
+
+        send(
+            &mut racing_wheel,
+            Report::<CreateNewEffect>::ID,
+            EffectType::ConstantForce as u8,
+            &[0, 0],
+        );
+        let step = TICK_MS as f32 / 100.0;
+        assert!((racing_wheel.update_idle_level(TICK_MS) - (1.0 - step)).abs() < 1e-6);
+        for _ in 1..100 / TICK_MS {
+            racing_wheel.update_idle_level(TICK_MS);
+        }
+        assert!(racing_wheel.idle_level < 1e-6);
+
+        // Nothing happens until the timeout has passed, then the level fades in
+        while racing_wheel.idle_time_ms + TICK_MS < 1000 {
+            assert_eq!(racing_wheel.update_idle_level(TICK_MS), 0.0);
+        }
+        assert!((racing_wheel.update_idle_level(TICK_MS) - step).abs() < 1e-6);
+        for _ in 1..100 / TICK_MS {
+            racing_wheel.update_idle_level(TICK_MS);
+        }
+        assert!((racing_wheel.idle_level - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn idle_level_fades_out_while_effect_plays() {
+        let mut config = test_config();
+        config.idle_timeout_s = 1;
+        config.idle_fade_ms = 100;
+        let mut racing_wheel = RacingWheel::new(config);
+        assert_eq!(racing_wheel.update_idle_level(TICK_MS), 1.0);
+
+        racing_wheel.pid_state_report.effect_playing = true;
+        for _ in 0..100 / TICK_MS {
+            racing_wheel.update_idle_level(TICK_MS);
+        }
+        assert!(racing_wheel.idle_level.abs() < 1e-6);
+
+        // Playing restarts the timeout
+        racing_wheel.pid_state_report.effect_playing = false;
+        assert_eq!(racing_wheel.update_idle_level(TICK_MS), 0.0);
+        assert_eq!(racing_wheel.idle_time_ms, TICK_MS);
+    }
+
+    #[test]
+    fn direction_is_decoded_in_descriptor_range() {
+        // The descriptor declares the direction instances as -120..=120, which is -180 to 180
+        // degrees in polar mode
+        let direction_output = |direction_enable: bool, instances: (i8, i8)| {
+            let mut racing_wheel = RacingWheel::new(test_config());
+            set_device_gain(&mut racing_wheel, 1.0);
+            let set_effect = SetEffect {
+                axis_y_enable: true,
+                direction_enable,
+                direction_instance_1: instances.0 as u8,
+                direction_instance_2: instances.1 as u8,
+                ..SetEffect::default()
+            };
+            let index = create_effect(&mut racing_wheel, 0.5, set_effect);
+            effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+            racing_wheel.advance(TICK_MS);
+            racing_wheel.get_force_feedback()
+        };
+
+        assert!((direction_output(true, (60, 0)) - 0.5).abs() < 1e-2);
+        assert!((direction_output(true, (-60, 0)) + 0.5).abs() < 1e-2);
+        assert!(direction_output(true, (120, 0)).abs() < 1e-2);
+
+        // Cartesian directions are the components of the direction vector
+        assert!((direction_output(false, (120, 0)) - 0.5).abs() < 1e-2);
+        assert!((direction_output(false, (-120, 0)) + 0.5).abs() < 1e-2);
+        assert!((direction_output(false, (-120, 120)) + 0.5 * FRAC_1_SQRT_2).abs() < 1e-2);
+        assert!(direction_output(false, (0, -120)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn restarting_running_effects_keeps_them_playing() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        set_device_gain(&mut racing_wheel, 1.0);
+        let first = create_constant_force(&mut racing_wheel, 0.25);
+        let second = create_constant_force(&mut racing_wheel, 0.5);
+
+        effect_operation(&mut racing_wheel, second, EffectOperation::EffectStart);
+        effect_operation(&mut racing_wheel, second, EffectOperation::EffectStart);
+        racing_wheel.advance(TICK_MS);
+        assert!((racing_wheel.get_force_feedback() - 0.5).abs() < 1e-3);
+
+        // Restart the last of two running effects
+        effect_operation(&mut racing_wheel, first, EffectOperation::EffectStart);
+        effect_operation(&mut racing_wheel, second, EffectOperation::EffectStart);
+        racing_wheel.advance(TICK_MS);
+        assert!((racing_wheel.get_force_feedback() - 0.75).abs() < 1e-3);
+    }
+
+    #[test]
+    fn restart_with_loop_count_rearms_loops() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        set_device_gain(&mut racing_wheel, 1.0);
+        let index = create_effect(
+            &mut racing_wheel,
+            0.5,
+            SetEffect {
+                duration: Some(10),
+                ..SetEffect::default()
+            },
+        );
+
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+        assert!(advance_for(&mut racing_wheel, 8));
+
+        // Restarted from the beginning, now playing three times
+        effect_operation_looped(&mut racing_wheel, index, EffectOperation::EffectStart, 3);
+        assert!(advance_for(&mut racing_wheel, 28));
+        assert!((racing_wheel.get_force_feedback() - 0.5).abs() < 1e-3);
+        assert!(!advance_for(&mut racing_wheel, 2));
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+    }
+
+    #[test]
+    fn held_trigger_button_repeats_effect() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        set_device_gain(&mut racing_wheel, 1.0);
+        let mut buttons = [false; 8];
+        create_effect(
+            &mut racing_wheel,
+            0.5,
+            SetEffect {
+                duration: Some(15),
+                trigger_button: 1,
+                trigger_repeat_interval: 10,
+                ..SetEffect::default()
+            },
+        );
+
+        assert!(!advance_for(&mut racing_wheel, 4));
+
+        // Restarted every 10 ms while the button is held, before the 15 ms duration ends
+        buttons[0] = true;
+        racing_wheel.set_buttons(buttons);
+        assert!(advance_for(&mut racing_wheel, 40));
+        assert!((racing_wheel.get_force_feedback() - 0.5).abs() < 1e-3);
+
+        buttons[0] = false;
+        racing_wheel.set_buttons(buttons);
+        assert!(!advance_for(&mut racing_wheel, 16));
+    }
+
+    #[test]
+    fn trigger_repeat_restarts_ended_effect() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        set_device_gain(&mut racing_wheel, 1.0);
+        let mut buttons = [false; 8];
+        create_effect(
+            &mut racing_wheel,
+            0.5,
+            SetEffect {
+                duration: Some(4),
+                trigger_button: 2,
+                trigger_repeat_interval: 10,
+                ..SetEffect::default()
+            },
+        );
+
+        buttons[1] = true;
+        racing_wheel.set_buttons(buttons);
+        assert!(advance_for(&mut racing_wheel, 2));
+        assert!(!advance_for(&mut racing_wheel, 6));
+        assert!(advance_for(&mut racing_wheel, 4));
+    }
+
+    #[test]
+    fn evicted_effect_is_reported_until_sent() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        let indices = start_simultaneous_effects(&mut racing_wheel);
+        let index = create_constant_force(&mut racing_wheel, 0.1);
+
+        // The oldest effect of the same priority makes room
+        let started = effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+        assert_eq!(started, Some(true));
+        assert!(is_running(&racing_wheel, index));
+        assert!(!is_running(&racing_wheel, indices[0]));
+        assert!(racing_wheel.pid_state_event());
+        assert!(!racing_wheel.pid_state_report.effect_playing);
+        assert_eq!(racing_wheel.pid_state_report.effect_block_index, indices[0]);
+
+        racing_wheel.advance(TICK_MS);
+        assert!(!racing_wheel.pid_state_report.effect_playing);
+
+        racing_wheel.pid_state_sent();
+        racing_wheel.advance(TICK_MS);
+        assert!(racing_wheel.pid_state_event());
+        assert!(racing_wheel.pid_state_report.effect_playing);
+    }
+
+    #[test]
+    fn refused_start_is_reported() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        let indices = start_simultaneous_effects(&mut racing_wheel);
+        let index = create_sine(&mut racing_wheel, SetEffect::default());
+
+        // All running effects outrank the sine
+        let started = effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+        assert_eq!(started, Some(false));
+        assert!(!is_running(&racing_wheel, index));
+        assert!(indices
+            .iter()
+            .all(|index| is_running(&racing_wheel, *index)));
+        assert!(racing_wheel.pid_state_event());
+        assert!(!racing_wheel.pid_state_report.effect_playing);
+        assert_eq!(racing_wheel.pid_state_report.effect_block_index, index);
+    }
+
+    #[test]
+    fn refused_trigger_start_is_reported() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        start_simultaneous_effects(&mut racing_wheel);
+        let index = create_sine(
+            &mut racing_wheel,
+            SetEffect {
+                trigger_button: 1,
+                ..SetEffect::default()
+            },
+        );
+        racing_wheel.advance(TICK_MS);
+        racing_wheel.pid_state_event();
+
+        let mut buttons = [false; 8];
+        buttons[0] = true;
+        racing_wheel.set_buttons(buttons);
+        racing_wheel.advance(TICK_MS);
+        assert!(!is_running(&racing_wheel, index));
+        assert!(racing_wheel.pid_state_event());
+        assert!(!racing_wheel.pid_state_report.effect_playing);
+        assert_eq!(racing_wheel.pid_state_report.effect_block_index, index);
+    }
+
+    #[test]
+    fn freed_effect_can_not_be_started() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        set_device_gain(&mut racing_wheel, 1.0);
+        let index = create_constant_force(&mut racing_wheel, 0.5);
+
+        send(&mut racing_wheel, Report::<PIDBlockFree>::ID, index, &[]);
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+
+        racing_wheel.advance(TICK_MS);
+        assert!(!racing_wheel.pid_state_report.effect_playing);
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+    }
+
+    #[test]
+    fn paused_device_outputs_no_force() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+        set_device_gain(&mut racing_wheel, 1.0);
+        let index = create_constant_force(&mut racing_wheel, 0.5);
+        effect_operation(&mut racing_wheel, index, EffectOperation::EffectStart);
+
+        racing_wheel
+            .report_request_out(
+                Report::<PIDDeviceControl>::ID,
+                &[0x0C, DeviceControl::DevicePause as u8],
+            )
+            .unwrap();
+        racing_wheel.advance(TICK_MS);
+        assert_eq!(racing_wheel.get_force_feedback(), 0.0);
+
+        racing_wheel
+            .report_request_out(
+                Report::<PIDDeviceControl>::ID,
+                &[0x0C, DeviceControl::DeviceContinue as u8],
+            )
+            .unwrap();
+        racing_wheel.advance(TICK_MS);
+        assert!((racing_wheel.get_force_feedback() - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wheel_device_control_raises_events_once() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+
+        send_wheel_device_control(&mut racing_wheel, WheelDeviceControl::WriteConfig);
+        send_wheel_device_control(&mut racing_wheel, WheelDeviceControl::Reboot);
+
+        assert!(racing_wheel.write_config_event());
+        assert!(!racing_wheel.write_config_event());
+        assert!(racing_wheel.reboot_device_event());
+        assert!(!racing_wheel.reset_steering_event());
+    }
+
+    #[test]
+    fn unknown_reports_are_not_handled() {
+        let mut racing_wheel = RacingWheel::new(test_config());
+
+        assert_eq!(
+            send(
+                &mut racing_wheel,
+                ReportID(ReportType::Output, 0x7F),
+                1,
+                &[]
+            ),
+            None
+        );
+    }
+}
@@ -1,7 +1,9 @@
+use super::HOST_MANAGED_POOL;
+
 pub const LOGICAL_MAXIMUM: i32 = 10_000;
 
 #[rustfmt::skip]
-pub const RACING_WHEEL_DESCRIPTOR: &[u8] = &[
+const DEVICE_MANAGED_DESCRIPTOR: &[u8] = &[
     // -- Joystick report --
 
     0x05, 0x01,        // Usage Page (Generic Desktop Ctrls)
@@ -44,11 +46,12 @@ pub const RACING_WHEEL_DESCRIPTOR: &[u8] = &[
     0x36, 0xF0, 0xD8,  //     Physical Minimum (-10000)
     0x46, 0x10, 0x27,  //     Physical Maximum (10000)
     0x75, 0x10,        //     Report Size (16)
-    0x95, 0x01,        //     Report Count (1)
+    0x95, 0x02,        //     Report Count (2)
     0x65, 0x00,        //     Unit (None)
     0x55, 0xFF,        //     Unit Exponent
     0xA1, 0x00,        //     Collection (Physical)
     0x09, 0x01,        //       Usage (FFB)
+    0x09, 0x02,        //       Usage (Oscillation suppression)
     0x81, 0x02,        //       Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
     0xC0,              //     End Collection
     0x55, 0x00,        //     Unit Exponent (0)
@@ -182,11 +185,11 @@ pub const RACING_WHEEL_DESCRIPTOR: &[u8] = &[
     0x0B, 0x01, 0x00, 0x0A, 0x00,  //       Usage (Ordinals: Instance 1)
     0x0B, 0x02, 0x00, 0x0A, 0x00,  //       Usage (Ordinals: Instance 2)
     0x66, 0x44, 0x00,  //     Unit (System: English Rotation, Length: Degrees)
-    0x55, 0xFF,        //     Unit Exponent (-1)
-    0x15, 0x00,        //       Logical Minimum (0)
-    0x26, 0xFF, 0x00,  //       Logical Maximum (255)
-    0x35, 0x00,        //       Physical Minimum (0)
-    0x47, 0xA0, 0x8C, 0x00, 0x00,  //       Physical Maximum (35999)
+    0x55, 0xFE,        //     Unit Exponent (-2)
+    0x15, 0x88,        //       Logical Minimum (-120)
+    0x25, 0x78,        //       Logical Maximum (120)
+    0x36, 0xB0, 0xB9,  //       Physical Minimum (-18000)
+    0x46, 0x50, 0x46,  //       Physical Maximum (18000)
     0x66, 0x00, 0x00,  //       Unit (None)
     0x75, 0x08,        //       Report Size (8)
     0x95, 0x02,        //       Report Count (2)
@@ -705,3 +708,99 @@ pub const RACING_WHEEL_DESCRIPTOR: &[u8] = &[
     
     0xC0,              // End Collection
 ];
+
+// In host managed mode the parameter reports address their parameter block by its byte offset in
+// the pool, so a 16 bit Parameter Block Offset takes the place of their Effect Block Index.
+pub const RACING_WHEEL_DESCRIPTOR: &[u8] = if HOST_MANAGED_POOL {
+    &HOST_MANAGED_DESCRIPTOR
+} else {
+    DEVICE_MANAGED_DESCRIPTOR
+};
+
+// Set Envelope, Set Condition, Set Periodic, Set Constant Force, Set Ramp Force and Set Custom
+// Force reports
+const PARAMETER_REPORT_IDS: [u8; 6] = [0x02, 0x03, 0x04, 0x05, 0x06, 0x09];
+
+#[rustfmt::skip]
+const EFFECT_BLOCK_INDEX: [u8; 16] = [
+    0x09, 0x22,        // Usage (Effect Block Index)
+    0x15, 0x01,        // Logical Minimum (1)
+    0x25, 0x28,        // Logical Maximum (40)
+    0x35, 0x01,        // Physical Minimum (1)
+    0x45, 0x28,        // Physical Maximum (40)
+    0x75, 0x08,        // Report Size (8)
+    0x95, 0x01,        // Report Count (1)
+    0x91, 0x02,        // Output (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Non-volatile)
+];
+
+#[rustfmt::skip]
+const PARAMETER_BLOCK_OFFSET: [u8; 18] = [
+    0x09, 0x23,        // Usage (Parameter Block Offset)
+    0x15, 0x00,        // Logical Minimum (0)
+    0x26, 0xFD, 0x7F,  // Logical Maximum (32765)
+    0x35, 0x00,        // Physical Minimum (0)
+    0x46, 0xFD, 0x7F,  // Physical Maximum (32765)
+    0x75, 0x10,        // Report Size (16)
+    0x95, 0x01,        // Report Count (1)
+    0x91, 0x02,        // Output (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Non-volatile)
+];
+
+const HOST_MANAGED_DESCRIPTOR_SIZE: usize = DEVICE_MANAGED_DESCRIPTOR.len()
+    + PARAMETER_REPORT_IDS.len() * (PARAMETER_BLOCK_OFFSET.len() - EFFECT_BLOCK_INDEX.len());
+
+const HOST_MANAGED_DESCRIPTOR: [u8; HOST_MANAGED_DESCRIPTOR_SIZE] = host_managed_descriptor();
+
+// Copies the device managed descriptor, replacing the Effect Block Index that directly follows the
+// Report ID of every parameter report
+const fn host_managed_descriptor() -> [u8; HOST_MANAGED_DESCRIPTOR_SIZE] {
+    let source = DEVICE_MANAGED_DESCRIPTOR;
+    let mut descriptor = [0; HOST_MANAGED_DESCRIPTOR_SIZE];
+    let (mut i, mut j) = (0, 0);
+    while i < source.len() {
+        if is_parameter_report_id(source, i) && starts_with(source, i + 2, &EFFECT_BLOCK_INDEX) {
+            descriptor[j] = source[i];
+            descriptor[j + 1] = source[i + 1];
+            let mut k = 0;
+            while k < PARAMETER_BLOCK_OFFSET.len() {
+                descriptor[j + 2 + k] = PARAMETER_BLOCK_OFFSET[k];
+                k += 1;
+            }
+            i += 2 + EFFECT_BLOCK_INDEX.len();
+            j += 2 + PARAMETER_BLOCK_OFFSET.len();
+        } else {
+            descriptor[j] = source[i];
+            i += 1;
+            j += 1;
+        }
+    }
+    assert!(j == HOST_MANAGED_DESCRIPTOR_SIZE);
+    descriptor
+}
+
+const fn is_parameter_report_id(descriptor: &[u8], i: usize) -> bool {
+    if i + 1 >= descriptor.len() || descriptor[i] != 0x85 {
+        return false;
+    }
+    let mut k = 0;
+    while k < PARAMETER_REPORT_IDS.len() {
+        if descriptor[i + 1] == PARAMETER_REPORT_IDS[k] {
+            return true;
+        }
+        k += 1;
+    }
+    false
+}
+
+const fn starts_with(descriptor: &[u8], i: usize, items: &[u8]) -> bool {
+    if i + items.len() > descriptor.len() {
+        return false;
+    }
+    let mut k = 0;
+    while k < items.len() {
+        if descriptor[i + k] != items[k] {
+            return false;
+        }
+        k += 1;
+    }
+    true
+}
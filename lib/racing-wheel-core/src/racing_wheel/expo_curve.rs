@@ -0,0 +1,94 @@
+// Shapes the motor output with the expo of the config. With floating point hardware the expo is
+// calculated exactly every tick. With the fixed-point feature the target has no floating point
+// unit, so the expo is sampled into a table when the config is set. The table has evenly spaced
+// points within every octave of the torque, which keeps the error small near zero, where the
+// expo curve is steepest.
+#[cfg(not(test))]
+use micromath::F32Ext;
+
+#[cfg(feature = "fixed-point")]
+const OCTAVES: usize = 16;
+#[cfg(feature = "fixed-point")]
+const POINTS_PER_OCTAVE: usize = 8;
+#[cfg(feature = "fixed-point")]
+const CURVE_SIZE: usize = OCTAVES * POINTS_PER_OCTAVE + 1;
+
+#[derive(Clone, Copy)]
+pub struct ExpoCurve {
+    #[cfg(not(feature = "fixed-point"))]
+    expo: f32,
+    // Output at the torque of every point, the last point is full torque. Below the lowest
+    // octave the output is interpolated towards zero.
+    #[cfg(feature = "fixed-point")]
+    curve: [f32; CURVE_SIZE],
+}
+
+impl ExpoCurve {
+    #[cfg(not(feature = "fixed-point"))]
+    pub fn new(expo: f32) -> Self {
+        Self { expo }
+    }
+
+    #[cfg(feature = "fixed-point")]
+    pub fn new(expo: f32) -> Self {
+        let mut curve = [0.0; CURVE_SIZE];
+        for (i, point) in curve.iter_mut().enumerate() {
+            *point = f32::powf(point_torque(i), expo);
+        }
+        Self { curve }
+    }
+
+    // Motor output for a torque from 0 to 1
+    #[cfg(not(feature = "fixed-point"))]
+    pub fn apply(&self, torque: f32) -> f32 {
+        f32::powf(torque, self.expo)
+    }
+
+    #[cfg(feature = "fixed-point")]
+    pub fn apply(&self, torque: f32) -> f32 {
+        let lowest_torque = point_torque(0);
+        if torque >= 1.0 {
+            return self.curve[CURVE_SIZE - 1];
+        } else if torque < lowest_torque {
+            return f32::max(torque, 0.0) / lowest_torque * self.curve[0];
+        }
+
+        // The exponent of the torque is its octave, the mantissa the position within it
+        let bits = torque.to_bits();
+        let octave = 126 - ((bits >> 23) & 0xFF) as usize;
+        let position = (bits & 0x7F_FFFF) as f32 / (1 << 23) as f32 * POINTS_PER_OCTAVE as f32;
+        let segment = position as usize;
+        let index = (OCTAVES - 1 - octave) * POINTS_PER_OCTAVE + segment;
+        let fraction = position - segment as f32;
+        self.curve[index] + (self.curve[index + 1] - self.curve[index]) * fraction
+    }
+}
+
+// Torque at a point of the curve, point 0 is the start of the lowest octave
+#[cfg(feature = "fixed-point")]
+fn point_torque(index: usize) -> f32 {
+    let octave = OCTAVES - index / POINTS_PER_OCTAVE;
+    let step = (index % POINTS_PER_OCTAVE) as f32 / POINTS_PER_OCTAVE as f32;
+    (1.0 + step) / (1_u32 << octave) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_torques_are_accurate() {
+        for expo in [0.5, 0.9, 1.0, 1.5, 2.0] {
+            let curve = ExpoCurve::new(expo);
+            for torque in [1e-4, 5e-4, 0.001, 0.005, 0.01, 0.03, 0.1, 0.3, 0.7, 1.0] {
+                let exact = f32::powf(torque, expo);
+                assert!((curve.apply(torque) - exact).abs() < 0.005 * exact);
+            }
+        }
+    }
+
+    #[test]
+    fn no_torque_has_no_output() {
+        assert_eq!(ExpoCurve::new(0.5).apply(0.0), 0.0);
+    }
+}
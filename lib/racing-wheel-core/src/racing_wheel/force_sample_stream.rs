@@ -0,0 +1,116 @@
+use force_feedback::renderer::TypedEffect;
+
+// Ring buffer of force samples streamed by the host with Download Force Sample reports. The
+// samples are played back one at a time at the sample period of the custom force effect that
+// consumes the stream. When the buffer is full the oldest sample is dropped to keep the latency
+// bounded.
+pub struct ForceSampleStream<const N: usize> {
+    samples: [f32; N],
+    start: usize,
+    len: usize,
+    current: f32,
+    elapsed: u32,
+}
+
+impl<const N: usize> ForceSampleStream<N> {
+    pub fn new() -> Self {
+        Self {
+            samples: [0.0; N],
+            start: 0,
+            len: 0,
+            current: 0.0,
+            elapsed: 0,
+        }
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        if self.len == N {
+            self.start = (self.start + 1) % N;
+            self.len -= 1;
+        }
+
+        self.samples[(self.start + self.len) % N] = sample;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<f32> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let sample = self.samples[self.start];
+        self.start = (self.start + 1) % N;
+        self.len -= 1;
+        Some(sample)
+    }
+
+    // Advances playback by delta_time_ms and returns the sample currently being played. The last
+    // sample is held if the host has not sent a new one in time.
+    pub fn advance(&mut self, delta_time_ms: u32, sample_period: u32) -> f32 {
+        self.elapsed += delta_time_ms;
+        while self.elapsed >= sample_period {
+            self.elapsed -= sample_period;
+            if let Some(sample) = self.pop() {
+                self.current = sample;
+            }
+        }
+
+        self.current
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+// A custom force effect without any downloaded samples plays the streamed force samples instead.
+pub fn is_streamed(effect: &TypedEffect) -> bool {
+    match effect {
+        TypedEffect::CustomForce(effect) => effect
+            .custom_force
+            .map(|custom_force| custom_force.sample_count == 0)
+            .unwrap_or(true),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_played_at_sample_period() {
+        let mut stream = ForceSampleStream::<4>::new();
+        stream.push(0.25);
+        stream.push(0.5);
+
+        assert_eq!(stream.advance(2, 4), 0.0);
+        assert_eq!(stream.advance(2, 4), 0.25);
+        assert_eq!(stream.advance(2, 4), 0.25);
+        assert_eq!(stream.advance(2, 4), 0.5);
+    }
+
+    #[test]
+    fn overrun_drops_oldest_sample() {
+        let mut stream = ForceSampleStream::<2>::new();
+        stream.push(0.25);
+        stream.push(0.5);
+        stream.push(0.75);
+
+        assert_eq!(stream.advance(1, 1), 0.5);
+        assert_eq!(stream.advance(1, 1), 0.75);
+    }
+
+    #[test]
+    fn underrun_holds_last_sample() {
+        let mut stream = ForceSampleStream::<2>::new();
+        stream.push(0.5);
+
+        assert_eq!(stream.advance(1, 1), 0.5);
+        assert_eq!(stream.advance(5, 1), 0.5);
+
+        // Playback continues with the next sample as soon as it arrives
+        stream.push(-0.5);
+        assert_eq!(stream.advance(1, 1), -0.5);
+    }
+}
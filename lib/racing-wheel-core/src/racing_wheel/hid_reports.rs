@@ -30,8 +30,8 @@ impl HIDReport for Report<RacingWheelState> {
     const ID: ReportID = ReportID(ReportType::Input, 0x01);
 }
 
-impl HIDReportIn<8> for Report<RacingWheelState> {
-    fn report_bytes(&self) -> [u8; 8] {
+impl HIDReportIn<10> for Report<RacingWheelState> {
+    fn report_bytes(&self) -> [u8; 10] {
         [
             Self::ID.1,
             bitflags(&self.buttons),
@@ -41,6 +41,8 @@ impl HIDReportIn<8> for Report<RacingWheelState> {
             f32_to_2_bytes(self.throttle)[1],
             f32_to_2_bytes(self.ffb)[0],
             f32_to_2_bytes(self.ffb)[1],
+            f32_to_2_bytes(self.oscillation_suppression)[0],
+            f32_to_2_bytes(self.oscillation_suppression)[1],
         ]
     }
 }
@@ -81,7 +83,7 @@ impl HIDReportRAM<19> for Report<SetEffect> {
         let sample_period = u16::from_le_bytes([*ram.get(5)?, *ram.get(6)?]);
         Some(Report(SetEffect {
             effect_block_index,
-            effect_type: EffectType::try_from(*ram.get(0)?).ok()?,
+            effect_type: EffectType::try_from(*ram.first()?).ok()?,
             duration: if duration == 0 || duration == u16::MAX {
                 None
             } else {
@@ -204,9 +206,9 @@ impl HIDReportRAM<13> for Report<SetCondition> {
     fn from_ram(ram: &[u8], effect_block_index: u8) -> Option<Self> {
         Some(Report(SetCondition {
             effect_block_index,
-            parameter_block_offset: bits(*ram.get(0)?, 0, 4),
-            type_specific_block_offset_instance_1: bits(*ram.get(0)?, 4, 2),
-            type_specific_block_offset_instance_2: bits(*ram.get(0)?, 6, 2),
+            parameter_block_offset: bits(*ram.first()?, 0, 4),
+            type_specific_block_offset_instance_1: bits(*ram.first()?, 4, 2),
+            type_specific_block_offset_instance_2: bits(*ram.first()?, 6, 2),
             cp_offset: f32_from_2_bytes(&ram[1..])?,
             positive_coefficient: f32_from_2_bytes(&ram[3..])?,
             negative_coefficient: f32_from_2_bytes(&ram[5..])?,
@@ -218,7 +220,7 @@ impl HIDReportRAM<13> for Report<SetCondition> {
 
     fn to_ram(&self) -> [u8; 13] {
         [
-            (self.parameter_block_offset & 0b1111) << 0
+            (self.parameter_block_offset & 0b1111)
                 | (self.type_specific_block_offset_instance_1 & 0b11) << 4
                 | (self.type_specific_block_offset_instance_2 & 0b11) << 6,
             ((self.cp_offset * 10_000.0) as i16).to_le_bytes()[0],
@@ -343,7 +345,7 @@ impl HIDReportRAM<15> for Report<CustomForceData> {
     fn from_ram(ram: &[u8], effect_block_index: u8) -> Option<Self> {
         Some(Report(CustomForceData {
             effect_block_index,
-            custom_force_data_offset: u16::from_le_bytes([*ram.get(0)?, *ram.get(1)?]),
+            custom_force_data_offset: u16::from_le_bytes([*ram.first()?, *ram.get(1)?]),
             byte_count: *ram.get(2)?,
             custom_force_data: ram.get(3..(3 + 12))?.try_into().unwrap_or_default(),
         }))
@@ -433,7 +435,7 @@ impl HIDReportOut for Report<DeviceGain> {
 }
 
 impl HIDReport for Report<SetCustomForce> {
-    const ID: ReportID = ReportID(ReportType::Output, 0x0E);
+    const ID: ReportID = ReportID(ReportType::Output, 0x09);
 }
 
 impl HIDReportOut for Report<SetCustomForce> {
@@ -446,8 +448,8 @@ impl HIDReportRAM<4> for Report<SetCustomForce> {
     fn from_ram(ram: &[u8], effect_block_index: u8) -> Option<Self> {
         Some(Report(SetCustomForce {
             effect_block_index,
-            custom_force_data_offset: u16::from_le_bytes([*ram.get(1)?, *ram.get(2)?]),
-            sample_count: u16::from_le_bytes([*ram.get(3)?, *ram.get(4)?]),
+            custom_force_data_offset: u16::from_le_bytes([*ram.first()?, *ram.get(1)?]),
+            sample_count: u16::from_le_bytes([*ram.get(2)?, *ram.get(3)?]),
         }))
     }
 
@@ -542,8 +544,8 @@ impl HIDReportOut for Report<Config> {
     }
 }
 
-impl HIDReportIn<63> for Report<Config> {
-    fn report_bytes(&self) -> [u8; 63] {
+impl HIDReportIn<{ Config::REPORT_SIZE }> for Report<Config> {
+    fn report_bytes(&self) -> [u8; Config::REPORT_SIZE] {
         self.into_bytes(Self::ID.1)
     }
 }
@@ -555,13 +557,13 @@ impl HIDReport for Report<WheelDeviceControl> {
 impl HIDReportOut for Report<WheelDeviceControl> {
     fn into_report(bytes: &[u8]) -> Option<Self> {
         WheelDeviceControl::try_from(*bytes.get(1)?)
-            .map(|wdc| Report(wdc))
+            .map(Report)
             .ok()
     }
 }
 
 fn f32_from_2_bytes(bytes: &[u8]) -> Option<f32> {
-    Some(i16::from_le_bytes([*bytes.get(0)?, *bytes.get(1)?]) as f32 / LOGICAL_MAXIMUM as f32)
+    Some(i16::from_le_bytes([*bytes.first()?, *bytes.get(1)?]) as f32 / LOGICAL_MAXIMUM as f32)
 }
 
 fn f32_to_2_bytes(value: f32) -> [u8; 2] {
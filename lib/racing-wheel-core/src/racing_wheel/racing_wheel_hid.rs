@@ -1,5 +1,6 @@
 use super::{
-    descriptor::RACING_WHEEL_DESCRIPTOR, hid_reports::Report, ram_pool::RAMPool, RacingWheel, RunningEffect, MAX_SIMULTANEOUS_EFFECTS
+    descriptor::RACING_WHEEL_DESCRIPTOR, hid_reports::Report, ram_pool::RAMPool, RacingWheel,
+    RunningEffect, HOST_MANAGED_POOL, MAX_SIMULTANEOUS_EFFECTS,
 };
 use crate::misc::FixedSet;
 use config::{config::Config, control::WheelDeviceControl};
@@ -10,6 +11,26 @@ use usb_hid_device::{
     hid_device::{HIDDeviceType, HIDReport, HIDReportOut, HIDReportRAM, ReportID},
 };
 
+impl RacingWheel {
+    // Allocates the effect requested by the last Create New Effect report, the result is
+    // returned to the host in the PID Block Load report.
+    pub(super) fn block_load(&mut self) -> PIDBlockLoad {
+        let (effect_block_index, block_load_status) = match self.next_effect.take() {
+            Some(next_effect) => match self.ram_pool.new_effect(next_effect.byte_count) {
+                Some(index) => (index, BlockLoadStatus::Success),
+                None => (0, BlockLoadStatus::Full),
+            },
+            None => (0, BlockLoadStatus::Error),
+        };
+
+        PIDBlockLoad {
+            effect_block_index,
+            block_load_status,
+            ram_pool_available: self.ram_pool.available() as u16,
+        }
+    }
+}
+
 impl HIDDeviceType for RacingWheel {
     fn descriptor() -> &'static [u8] {
         RACING_WHEEL_DESCRIPTOR
@@ -21,32 +42,7 @@ impl HIDDeviceType for RacingWheel {
         writer: GetReportInWriter<B>,
     ) -> Result<(), UsbError> {
         match report_id {
-            Report::<PIDBlockLoad>::ID => {
-                if let Some(_) = self.next_effect {
-                    self.next_effect = None;
-
-                    if let Some(index) = self.ram_pool.new_effect() {
-                        writer.accept(Report(PIDBlockLoad {
-                            effect_block_index: index as u8,
-                            block_load_status: BlockLoadStatus::Success,
-                            ram_pool_available: self.ram_pool.available() as u16,
-                        }))?;
-                    } else {
-                        writer.accept(Report(PIDBlockLoad {
-                            effect_block_index: 0,
-                            block_load_status: BlockLoadStatus::Full,
-                            ram_pool_available: self.ram_pool.available() as u16,
-                        }))?;
-                    }
-                } else {
-                    writer.accept(Report(PIDBlockLoad {
-                        effect_block_index: 0,
-                        block_load_status: BlockLoadStatus::Error,
-                        ram_pool_available: self.ram_pool.available() as u16,
-                    }))?;
-                }
-                Ok(())
-            }
+            Report::<PIDBlockLoad>::ID => writer.accept(Report(self.block_load())),
             Report::<PIDPool>::ID => writer.accept(Report(PIDPool {
                 ram_pool_size: self.ram_pool.pool_size() as u16,
                 simultaneous_effects_max: MAX_SIMULTANEOUS_EFFECTS as u8,
@@ -57,8 +53,8 @@ impl HIDDeviceType for RacingWheel {
                 param_block_size_set_constant_force: Report::<SetConstantForce>::RAM_SIZE as u8,
                 param_block_size_set_ramp_force: Report::<SetRampForce>::RAM_SIZE as u8,
                 param_block_size_set_custom_force: Report::<SetCustomForce>::RAM_SIZE as u8,
-                device_managed_pool: true,
-                shared_parameter_blocks: false,
+                device_managed_pool: !HOST_MANAGED_POOL,
+                shared_parameter_blocks: HOST_MANAGED_POOL,
                 isochronous_enable: true,
             })),
             Report::<Config>::ID => writer.accept(Report(self.get_config())),
@@ -70,90 +66,92 @@ impl HIDDeviceType for RacingWheel {
         match report_id {
             Report::<SetEffect>::ID => {
                 let report = Report::<SetEffect>::into_report(data).ok_or(())?;
-                let effect = self
-                    .ram_pool
-                    .get_effect_mut(report.effect_block_index)
-                    .ok_or(())?;
-                effect.effect_report = Some(*report);
+                self.ram_pool.set_effect(*report)?;
 
                 Ok(Some(true))
             }
             Report::<SetEnvelope>::ID => {
-                let report = Report::<SetEnvelope>::into_report(data).ok_or(())?;
-                let effect = self
-                    .ram_pool
-                    .get_effect_mut(report.effect_block_index)
-                    .ok_or(())?;
-                effect.parameter_2 = Some(EffectParameter::Envelope(*report));
+                let (address, report) = parameter_report::<SetEnvelope, _>(data).ok_or(())?;
+                self.ram_pool
+                    .set_parameter(address, 0, EffectParameter::Envelope(*report))?;
 
                 Ok(Some(true))
             }
             Report::<SetCondition>::ID => {
-                let report = Report::<SetCondition>::into_report(data).ok_or(())?;
-                let effect = self
-                    .ram_pool
-                    .get_effect_mut(report.effect_block_index)
-                    .ok_or(())?;
-                if report.parameter_block_offset == 0 {
-                    effect.parameter_1 = Some(EffectParameter::Condition(*report));
-                } else if report.parameter_block_offset == 1 {
-                    effect.parameter_2 = Some(EffectParameter::Condition(*report));
-                }
+                let (address, report) = parameter_report::<SetCondition, _>(data).ok_or(())?;
+                self.ram_pool.set_parameter(
+                    address,
+                    report.parameter_block_offset,
+                    EffectParameter::Condition(*report),
+                )?;
+
                 Ok(Some(true))
             }
             Report::<SetPeriodic>::ID => {
-                let report = Report::<SetPeriodic>::into_report(data).ok_or(())?;
-                let effect = self
-                    .ram_pool
-                    .get_effect_mut(report.effect_block_index)
-                    .ok_or(())?;
-                effect.parameter_1 = Some(EffectParameter::Periodic(*report));
+                let (address, report) = parameter_report::<SetPeriodic, _>(data).ok_or(())?;
+                self.ram_pool
+                    .set_parameter(address, 0, EffectParameter::Periodic(*report))?;
 
                 Ok(Some(true))
             }
             Report::<SetConstantForce>::ID => {
-                let report = Report::<SetConstantForce>::into_report(data).ok_or(())?;
-                let effect = self
-                    .ram_pool
-                    .get_effect_mut(report.effect_block_index)
-                    .ok_or(())?;
-                effect.parameter_1 = Some(EffectParameter::ConstantForce(*report));
+                let (address, report) = parameter_report::<SetConstantForce, _>(data).ok_or(())?;
+                self.ram_pool
+                    .set_parameter(address, 0, EffectParameter::ConstantForce(*report))?;
 
                 Ok(Some(true))
             }
             Report::<SetRampForce>::ID => {
-                let report = Report::<SetRampForce>::into_report(data).ok_or(())?;
-                let effect = self
-                    .ram_pool
-                    .get_effect_mut(report.effect_block_index)
-                    .ok_or(())?;
-                effect.parameter_1 = Some(EffectParameter::RampForce(*report));
+                let (address, report) = parameter_report::<SetRampForce, _>(data).ok_or(())?;
+                self.ram_pool
+                    .set_parameter(address, 0, EffectParameter::RampForce(*report))?;
 
                 Ok(Some(true))
             }
             Report::<CustomForceData>::ID => {
-                let _ = Report::<CustomForceData>::into_report(data).ok_or(())?;
+                let report = Report::<CustomForceData>::into_report(data).ok_or(())?;
+                let custom_force_data = report
+                    .custom_force_data
+                    .get(..report.byte_count as usize)
+                    .ok_or(())?;
+                self.ram_pool.write_custom_data(
+                    report.effect_block_index,
+                    report.custom_force_data_offset,
+                    custom_force_data,
+                )?;
+                Ok(Some(true))
+            }
+            Report::<DownloadForceSample>::ID => {
+                let report = Report::<DownloadForceSample>::into_report(data).ok_or(())?;
+                self.force_sample_stream
+                    .push(report.steering as f32 / i8::MAX as f32);
                 Ok(Some(true))
             }
             Report::<SetEffectOperation>::ID => {
                 let report = Report::<SetEffectOperation>::into_report(data).ok_or(())?;
-                match report.effect_operation {
-                    EffectOperation::EffectStart => {
-                        self.running_effects
-                            .insert(RunningEffect::new(report.effect_block_index));
-                    }
-                    EffectOperation::EffectStartSolo => {
+                let running_effect =
+                    RunningEffect::looped(report.effect_block_index, report.loop_count);
+                let started = match report.effect_operation {
+                    EffectOperation::EffectStartSolo
+                        if self.ram_pool.render_state(running_effect.index).is_ok() =>
+                    {
                         self.running_effects = FixedSet::new();
-                        self.running_effects
-                            .insert(RunningEffect::new(report.effect_block_index));
+                        self.start_effect(running_effect)
+                    }
+                    // Incomplete effects are rejected by start_effect
+                    EffectOperation::EffectStart | EffectOperation::EffectStartSolo => {
+                        self.start_effect(running_effect)
                     }
                     EffectOperation::EffectStop => {
                         self.running_effects
                             .remove(RunningEffect::new(report.effect_block_index));
+                        true
                     }
-                }
+                };
+                // Refused and evicted effects are reported in the PID state report
+                self.update_pid_state();
 
-                Ok(Some(true))
+                Ok(Some(started))
             }
             Report::<PIDBlockFree>::ID => {
                 let report = Report::<PIDBlockFree>::into_report(data).ok_or(())?;
@@ -169,15 +167,22 @@ impl HIDDeviceType for RacingWheel {
                     DeviceControl::DisableActuators => {
                         self.pid_state_report.actuators_enabled = false
                     }
-                    DeviceControl::StopAllEffects => self.running_effects = FixedSet::new(),
+                    DeviceControl::StopAllEffects => {
+                        self.running_effects = FixedSet::new();
+                        self.force_sample_stream.clear();
+                    }
                     DeviceControl::DeviceReset => {
                         self.pid_state_report = PIDState::default();
+                        self.stopped_effect = None;
                         self.running_effects = FixedSet::new();
+                        self.force_sample_stream.clear();
                         self.ram_pool = RAMPool::new();
                     }
                     DeviceControl::DevicePause => self.pid_state_report.device_paused = true,
                     DeviceControl::DeviceContinue => self.pid_state_report.device_paused = false,
                 }
+                self.update_pid_state();
+                self.pid_state_event = true;
 
                 Ok(Some(true))
             }
@@ -187,26 +192,29 @@ impl HIDDeviceType for RacingWheel {
                 Ok(Some(true))
             }
             Report::<SetCustomForce>::ID => {
-                let report = Report::<SetCustomForce>::into_report(data).ok_or(())?;
-                let effect = self
-                    .ram_pool
-                    .get_effect_mut(report.effect_block_index)
-                    .ok_or(())?;
-                effect.parameter_1 = Some(EffectParameter::CustomForce(*report));
+                let (address, report) = parameter_report::<SetCustomForce, _>(data).ok_or(())?;
+                self.ram_pool
+                    .set_parameter(address, 0, EffectParameter::CustomForce(*report))?;
 
                 Ok(Some(true))
             }
             Report::<PIDPoolMove>::ID => {
-                let _ = Report::<PIDPoolMove>::into_report(data).ok_or(())?;
+                let report = Report::<PIDPoolMove>::into_report(data).ok_or(())?;
+                self.ram_pool.move_custom_data(
+                    report.move_source,
+                    report.move_destination,
+                    report.move_length,
+                )?;
                 Ok(Some(true))
             }
             Report::<CreateNewEffect>::ID => {
                 let report = Report::<CreateNewEffect>::into_report(data).ok_or(())?;
                 self.next_effect = Some(*report);
+                self.idle_time_ms = 0;
                 Ok(Some(true))
             }
             Report::<Config>::ID => {
-                self.config = *Report::<Config>::into_report(data).ok_or(())?;
+                self.set_config(*Report::<Config>::into_report(data).ok_or(())?);
                 Ok(Some(true))
             }
             Report::<WheelDeviceControl>::ID => {
@@ -224,7 +232,24 @@ impl HIDDeviceType for RacingWheel {
     fn send_input_reports<B: UsbBus>(&mut self, writer: ReportWriter<B>) -> Result<(), UsbError> {
         writer.write_report(Report(self.racing_wheel_report.clone()))?;
         writer.write_report(Report(self.pid_state_report.clone()))?;
+        self.pid_state_sent();
 
         Ok(())
     }
 }
+
+// Parameter reports start with the effect block index. In host managed mode they start with the
+// byte offset of their parameter block in the pool instead, see the descriptor.
+fn parameter_report<T, const N: usize>(data: &[u8]) -> Option<(u16, Report<T>)>
+where
+    Report<T>: HIDReportRAM<N>,
+{
+    if HOST_MANAGED_POOL {
+        let offset = u16::from_le_bytes([*data.get(1)?, *data.get(2)?]);
+        Some((offset, Report::<T>::from_ram(data.get(3..)?, 0)?))
+    } else {
+        let effect_block_index = *data.get(1)?;
+        let report = Report::<T>::from_ram(data.get(2..)?, effect_block_index)?;
+        Some((effect_block_index as u16, report))
+    }
+}
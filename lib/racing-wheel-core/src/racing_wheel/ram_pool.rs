@@ -0,0 +1,608 @@
+use super::{hid_reports::Report, HOST_MANAGED_POOL};
+use core::convert::TryFrom;
+use force_feedback::{
+    effect::{Effect, EffectParameter},
+    render_state::RenderState,
+    renderer::{EffectError, TypedEffect},
+    reports::{SetCondition, SetEffect},
+};
+use usb_hid_device::hid_device::HIDReportRAM;
+
+// Every effect block is large enough to hold the largest combination of parameter blocks an
+// effect can use, which is a set effect block with two condition blocks. Sizes are in the units
+// declared in the PID Pool Report.
+pub const EFFECT_BLOCK_SIZE: usize =
+    Report::<SetEffect>::RAM_SIZE + 2 * Report::<SetCondition>::RAM_SIZE;
+
+// In host managed mode parameter blocks are stored separately from the effects, every parameter
+// block is large enough to hold the largest parameter block. The pool reported to the host starts
+// with the effect blocks, followed by the parameter blocks and the custom data, and the host
+// addresses parameter blocks by their byte offset in the pool.
+pub const PARAMETER_BLOCK_SIZE: usize = Report::<SetCondition>::RAM_SIZE;
+
+#[derive(Clone, Copy, Default)]
+struct CustomDataRegion {
+    offset: usize,
+    length: usize,
+}
+
+impl CustomDataRegion {
+    fn end(&self) -> usize {
+        self.offset + self.length
+    }
+}
+
+pub struct RAMPool<
+    const MAX_EFFECTS: usize,
+    const MAX_PARAMETER_BLOCKS: usize,
+    const CUSTOM_DATA_BUFFER_SIZE: usize,
+> {
+    custom_data_buffer: [u8; CUSTOM_DATA_BUFFER_SIZE],
+    custom_data_regions: [CustomDataRegion; MAX_EFFECTS],
+    effects: [Option<Effect>; MAX_EFFECTS],
+    render_states: [Result<RenderState, EffectError>; MAX_EFFECTS],
+    parameter_blocks: [Option<EffectParameter>; MAX_PARAMETER_BLOCKS],
+    parameter_block_offsets: [[u16; 2]; MAX_EFFECTS],
+}
+
+impl<
+        const MAX_EFFECTS: usize,
+        const MAX_PARAMETER_BLOCKS: usize,
+        const CUSTOM_DATA_BUFFER_SIZE: usize,
+    > RAMPool<MAX_EFFECTS, MAX_PARAMETER_BLOCKS, CUSTOM_DATA_BUFFER_SIZE>
+{
+    const PARAMETER_BLOCKS_OFFSET: usize = MAX_EFFECTS * Report::<SetEffect>::RAM_SIZE;
+
+    pub fn new() -> Self {
+        Self {
+            custom_data_buffer: [0; CUSTOM_DATA_BUFFER_SIZE],
+            custom_data_regions: [CustomDataRegion::default(); MAX_EFFECTS],
+            effects: [None; MAX_EFFECTS],
+            render_states: [Err(EffectError::MissingEffectReport); MAX_EFFECTS],
+            parameter_blocks: [None; MAX_PARAMETER_BLOCKS],
+            parameter_block_offsets: [[0; 2]; MAX_EFFECTS],
+        }
+    }
+
+    fn get_effect_mut(&mut self, effect_block_index: u8) -> Option<&mut Effect> {
+        self.effects
+            .get_mut(effect_block_index as usize - 1)?
+            .as_mut()
+    }
+
+    pub fn get_effect(&self, effect_block_index: u8) -> Option<&Effect> {
+        self.effects.get(effect_block_index as usize - 1)?.as_ref()
+    }
+
+    // The effect as it will be rendered, or the reason it can not be rendered yet
+    pub fn render_state(&self, effect_block_index: u8) -> Result<&RenderState, EffectError> {
+        let index = (effect_block_index as usize)
+            .checked_sub(1)
+            .ok_or(EffectError::MissingEffectReport)?;
+        self.render_states
+            .get(index)
+            .ok_or(EffectError::MissingEffectReport)?
+            .as_ref()
+            .map_err(|e| *e)
+    }
+
+    pub fn effects(&self) -> impl Iterator<Item = (u8, &Effect)> + '_ {
+        self.effects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| Some((i as u8 + 1, e.as_ref()?)))
+    }
+
+    // Allocates an effect block together with a custom data region of `byte_count` bytes.
+    // Returns None if there is no free effect block, or not enough free custom data memory.
+    pub fn new_effect(&mut self, byte_count: u16) -> Option<u8> {
+        let index = self.effects.iter().position(|e| e.is_none())?;
+        let length = byte_count as usize;
+        if length > self.custom_data_buffer.len() - self.custom_data_reserved() {
+            return None;
+        }
+
+        let offset = match self.find_custom_data_region(length) {
+            Some(offset) => offset,
+            None => self.compact_custom_data(),
+        };
+
+        self.effects[index] = Some(Effect::default());
+        self.custom_data_regions[index] = CustomDataRegion { offset, length };
+        self.update_render_state(index);
+        Some(index as u8 + 1)
+    }
+
+    // In host managed mode the parameter blocks belong to the host, they are kept until the host
+    // writes them again, even when no effect uses them anymore.
+    pub fn free_effect(&mut self, effect_block_index: u8) -> Result<(), ()> {
+        let index = (effect_block_index as usize).checked_sub(1).ok_or(())?;
+        let effect = self.effects.get_mut(index).ok_or(())?;
+        *effect = None;
+        self.custom_data_regions[index] = CustomDataRegion::default();
+        self.parameter_block_offsets[index] = [0; 2];
+        self.update_render_state(index);
+        Ok(())
+    }
+
+    // In host managed mode the host creates effects by setting them, and the type specific block
+    // offsets choose which parameter blocks the effect uses. An offset of zero, which is the
+    // first effect block, means that the instance is not used.
+    pub fn set_effect(&mut self, report: SetEffect) -> Result<(), ()> {
+        if !HOST_MANAGED_POOL {
+            let effect = self.get_effect_mut(report.effect_block_index).ok_or(())?;
+            effect.effect_report = Some(report);
+            self.update_render_state(report.effect_block_index as usize - 1);
+            return Ok(());
+        }
+
+        let index = (report.effect_block_index as usize)
+            .checked_sub(1)
+            .ok_or(())?;
+        let offsets = [
+            report.type_specific_block_offset_instance_1,
+            report.type_specific_block_offset_instance_2,
+        ];
+        let blocks = [
+            Self::parameter_block_index(offsets[0])?,
+            Self::parameter_block_index(offsets[1])?,
+        ];
+        let effect = Effect {
+            effect_report: Some(report),
+            parameter_1: blocks[0].and_then(|block| self.parameter_blocks[block]),
+            parameter_2: blocks[1].and_then(|block| self.parameter_blocks[block]),
+        };
+        *self.effects.get_mut(index).ok_or(())? = Some(effect);
+        self.parameter_block_offsets[index] = offsets;
+        self.update_render_state(index);
+        Ok(())
+    }
+
+    // Stores a parameter block. The parameter block offset selects the condition block, envelopes
+    // and the second condition block are the second instance of the effect. In device managed mode
+    // the address is the effect block index and the block belongs to that effect. In host managed
+    // mode the address is the byte offset of the parameter block in the pool, which can be written
+    // before the effects that use it are set, and every effect using the block is updated.
+    pub fn set_parameter(
+        &mut self,
+        address: u16,
+        parameter_block_offset: u8,
+        parameter: EffectParameter,
+    ) -> Result<(), ()> {
+        let instance = match (parameter, parameter_block_offset) {
+            (EffectParameter::Envelope(_), _) | (EffectParameter::Condition(_), 1) => 1,
+            (_, 0) => 0,
+            _ => return Ok(()),
+        };
+
+        if !HOST_MANAGED_POOL {
+            let effect_block_index = u8::try_from(address).map_err(|_| ())?;
+            let effect = self.get_effect_mut(effect_block_index).ok_or(())?;
+            if instance == 0 {
+                effect.parameter_1 = Some(parameter);
+            } else {
+                effect.parameter_2 = Some(parameter);
+            }
+            self.update_render_state(effect_block_index as usize - 1);
+            return Ok(());
+        }
+
+        // Which instance the block is depends on the effects using it
+        let block = Self::parameter_block_index(address)?.ok_or(())?;
+        self.parameter_blocks[block] = Some(parameter);
+
+        for (effect, offsets) in self.effects.iter_mut().zip(self.parameter_block_offsets) {
+            if let Some(effect) = effect.as_mut() {
+                if offsets[0] == address {
+                    effect.parameter_1 = Some(parameter);
+                }
+                if offsets[1] == address {
+                    effect.parameter_2 = Some(parameter);
+                }
+            }
+        }
+        self.update_render_states();
+        Ok(())
+    }
+
+    // Custom data offsets of an effect are relative to the region reserved for it
+    pub fn custom_data(&self, effect_block_index: u8) -> &[u8] {
+        self.custom_data_region(effect_block_index)
+            .and_then(|region| self.custom_data_buffer.get(region.offset..region.end()))
+            .unwrap_or(&[])
+    }
+
+    pub fn write_custom_data(
+        &mut self,
+        effect_block_index: u8,
+        offset: u16,
+        data: &[u8],
+    ) -> Result<(), ()> {
+        let region = self.custom_data_region(effect_block_index).ok_or(())?;
+        let start = region.offset + offset as usize;
+        let end = start + data.len();
+        if end > region.end() {
+            return Err(());
+        }
+
+        self.custom_data_buffer
+            .get_mut(start..end)
+            .ok_or(())?
+            .copy_from_slice(data);
+        Ok(())
+    }
+
+    // Moves a region of the custom data buffer. Custom data regions that lie entirely within the
+    // moved region are updated to point to the new location, custom force offsets are relative to
+    // the region so they stay valid. The move is rejected if it would overwrite the custom data of
+    // an effect that is not moved along.
+    pub fn move_custom_data(
+        &mut self,
+        source: u16,
+        destination: u16,
+        length: u16,
+    ) -> Result<(), ()> {
+        let (source, destination, length) =
+            (source as usize, destination as usize, length as usize);
+        if source + length > self.custom_data_buffer.len()
+            || destination + length > self.custom_data_buffer.len()
+        {
+            return Err(());
+        }
+
+        let moved = |region: &CustomDataRegion| {
+            region.length > 0 && region.offset >= source && region.end() <= source + length
+        };
+        let overwritten = self.custom_data_regions.iter().any(|region| {
+            region.length > 0
+                && !moved(region)
+                && destination < region.end()
+                && region.offset < destination + length
+        });
+        if overwritten {
+            return Err(());
+        }
+
+        self.custom_data_buffer
+            .copy_within(source..(source + length), destination);
+
+        for region in self.custom_data_regions.iter_mut() {
+            if moved(region) {
+                region.offset = region.offset - source + destination;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Memory that can still be allocated. Custom data can only be reserved together with an
+    // effect block, so nothing is available once all effect blocks are in use.
+    pub fn available(&self) -> usize {
+        let n_effects_available = self.effects.iter().filter(|e| e.is_none()).count();
+        if n_effects_available == 0 {
+            return 0;
+        }
+
+        if HOST_MANAGED_POOL {
+            let n_parameter_blocks_available =
+                self.parameter_blocks.iter().filter(|p| p.is_none()).count();
+
+            return n_effects_available * Report::<SetEffect>::RAM_SIZE
+                + n_parameter_blocks_available * PARAMETER_BLOCK_SIZE
+                + (self.custom_data_buffer.len() - self.custom_data_reserved());
+        }
+
+        n_effects_available * EFFECT_BLOCK_SIZE
+            + (self.custom_data_buffer.len() - self.custom_data_reserved())
+    }
+
+    pub fn pool_size(&self) -> usize {
+        if HOST_MANAGED_POOL {
+            MAX_EFFECTS * Report::<SetEffect>::RAM_SIZE
+                + MAX_PARAMETER_BLOCKS * PARAMETER_BLOCK_SIZE
+                + self.custom_data_buffer.len()
+        } else {
+            MAX_EFFECTS * EFFECT_BLOCK_SIZE + self.custom_data_buffer.len()
+        }
+    }
+
+    // The render state is built again every time a report for the effect is received, so rendering
+    // the effect every tick only uses precomputed values.
+    fn update_render_state(&mut self, index: usize) {
+        self.render_states[index] = match self.effects[index].as_ref() {
+            Some(effect) => TypedEffect::try_from(effect).map(RenderState::new),
+            None => Err(EffectError::MissingEffectReport),
+        };
+    }
+
+    fn update_render_states(&mut self) {
+        for index in 0..MAX_EFFECTS {
+            self.update_render_state(index);
+        }
+    }
+
+    // Byte offset of a parameter block in the pool, as the host addresses it
+    #[cfg(test)]
+    pub fn parameter_block_offset(block: usize) -> u16 {
+        (Self::PARAMETER_BLOCKS_OFFSET + block * PARAMETER_BLOCK_SIZE) as u16
+    }
+
+    // Index of the parameter block at a byte offset in the pool, None for an unused instance.
+    // Offsets that are not the start of a parameter block are rejected.
+    fn parameter_block_index(offset: u16) -> Result<Option<usize>, ()> {
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let offset = (offset as usize)
+            .checked_sub(Self::PARAMETER_BLOCKS_OFFSET)
+            .ok_or(())?;
+        let index = offset / PARAMETER_BLOCK_SIZE;
+        if offset % PARAMETER_BLOCK_SIZE != 0 || index >= MAX_PARAMETER_BLOCKS {
+            return Err(());
+        }
+        Ok(Some(index))
+    }
+
+    // Custom data is reserved when the effect is created, in both pool modes
+    fn custom_data_region(&self, effect_block_index: u8) -> Option<CustomDataRegion> {
+        let index = (effect_block_index as usize).checked_sub(1)?;
+        self.effects.get(index)?.as_ref()?;
+        self.custom_data_regions.get(index).copied()
+    }
+
+    fn custom_data_reserved(&self) -> usize {
+        self.custom_data_regions.iter().map(|r| r.length).sum()
+    }
+
+    // First fit search for a free custom data region of the given length
+    fn find_custom_data_region(&self, length: usize) -> Option<usize> {
+        let candidates =
+            core::iter::once(0).chain(self.custom_data_regions.iter().map(|r| r.end()));
+        candidates
+            .filter(|&offset| offset + length <= self.custom_data_buffer.len())
+            .filter(|&offset| {
+                self.custom_data_regions
+                    .iter()
+                    .all(|r| r.length == 0 || offset + length <= r.offset || offset >= r.end())
+            })
+            .min()
+    }
+
+    // Moves all custom data regions to the start of the buffer, in order of their offset, and
+    // returns the offset of the free space left at the end
+    fn compact_custom_data(&mut self) -> usize {
+        let mut free_offset = 0;
+        while let Some(index) = (0..MAX_EFFECTS)
+            .filter(|&i| {
+                let region = self.custom_data_regions[i];
+                region.length > 0 && region.offset >= free_offset
+            })
+            .min_by_key(|&i| self.custom_data_regions[i].offset)
+        {
+            let region = self.custom_data_regions[index];
+            self.custom_data_buffer
+                .copy_within(region.offset..region.end(), free_offset);
+            self.custom_data_regions[index].offset = free_offset;
+            free_offset += region.length;
+        }
+        free_offset
+    }
+}
+
+#[cfg(all(test, not(feature = "host-managed-pool")))]
+mod tests {
+    use super::*;
+
+    type TestPool = RAMPool<4, 0, 100>;
+
+    // Allocates an effect and fills its custom data with `value`
+    fn new_filled_effect(pool: &mut TestPool, byte_count: u16, value: u8) -> u8 {
+        let index = pool.new_effect(byte_count).unwrap();
+        pool.write_custom_data(index, 0, &[value; 100][..byte_count as usize])
+            .unwrap();
+        index
+    }
+
+    #[test]
+    fn new_effect_fails_when_custom_data_is_full() {
+        let mut pool = TestPool::new();
+
+        assert_eq!(pool.new_effect(90), Some(1));
+        assert_eq!(pool.new_effect(20), None);
+        assert_eq!(pool.new_effect(10), Some(2));
+        assert_eq!(pool.custom_data(2).len(), 10);
+        assert_eq!(pool.new_effect(1), None);
+        assert_eq!(pool.new_effect(0), Some(3));
+    }
+
+    #[test]
+    fn new_effect_fails_when_effect_blocks_are_full() {
+        let mut pool = TestPool::new();
+        for _ in 0..4 {
+            assert!(pool.new_effect(0).is_some());
+        }
+
+        assert_eq!(pool.new_effect(0), None);
+        assert_eq!(pool.available(), 0);
+        pool.free_effect(2).unwrap();
+        assert_eq!(pool.new_effect(10), Some(2));
+    }
+
+    #[test]
+    fn new_effect_fills_free_region_between_effects() {
+        let mut pool = TestPool::new();
+        let first = new_filled_effect(&mut pool, 30, 1);
+        let second = new_filled_effect(&mut pool, 30, 2);
+        let third = new_filled_effect(&mut pool, 30, 3);
+        pool.free_effect(second).unwrap();
+
+        let index = new_filled_effect(&mut pool, 20, 4);
+        assert_eq!(pool.custom_data_regions[index as usize - 1].offset, 30);
+        assert_eq!(pool.custom_data(first), &[1; 30]);
+        assert_eq!(pool.custom_data(third), &[3; 30]);
+    }
+
+    #[test]
+    fn new_effect_compacts_fragmented_custom_data() {
+        let mut pool = TestPool::new();
+        let first = new_filled_effect(&mut pool, 30, 1);
+        let second = new_filled_effect(&mut pool, 30, 2);
+        let third = new_filled_effect(&mut pool, 30, 3);
+        pool.free_effect(second).unwrap();
+
+        // 40 bytes are free, but only in a 30 and a 10 byte region
+        let index = pool.new_effect(40).unwrap();
+        assert_eq!(pool.custom_data(index).len(), 40);
+        assert_eq!(pool.custom_data(first), &[1; 30]);
+        assert_eq!(pool.custom_data(third), &[3; 30]);
+        assert_eq!(pool.custom_data_regions[third as usize - 1].offset, 30);
+        assert_eq!(pool.new_effect(1), None);
+    }
+
+    #[test]
+    fn move_custom_data_moves_effect_regions() {
+        let mut pool = TestPool::new();
+        let first = new_filled_effect(&mut pool, 20, 1);
+        let second = new_filled_effect(&mut pool, 20, 2);
+
+        pool.move_custom_data(20, 60, 20).unwrap();
+        assert_eq!(pool.custom_data_regions[second as usize - 1].offset, 60);
+        assert_eq!(pool.custom_data(second), &[2; 20]);
+
+        // Moving both regions over themselves
+        pool.move_custom_data(0, 10, 80).unwrap();
+        assert_eq!(pool.custom_data(first), &[1; 20]);
+        assert_eq!(pool.custom_data(second), &[2; 20]);
+    }
+
+    #[test]
+    fn move_custom_data_does_not_overwrite_other_effects() {
+        let mut pool = TestPool::new();
+        let first = new_filled_effect(&mut pool, 30, 1);
+        let second = new_filled_effect(&mut pool, 30, 2);
+
+        assert!(pool.move_custom_data(0, 20, 30).is_err());
+        assert!(pool.move_custom_data(0, 90, 30).is_err());
+        assert_eq!(pool.custom_data(first), &[1; 30]);
+        assert_eq!(pool.custom_data(second), &[2; 30]);
+    }
+}
+
+#[cfg(all(test, feature = "host-managed-pool"))]
+mod host_managed_tests {
+    use super::*;
+    use force_feedback::reports::SetConstantForce;
+
+    type TestPool = RAMPool<4, 4, 100>;
+
+    fn set_effect(pool: &mut TestPool, effect_block_index: u8, offset: u16) -> Result<(), ()> {
+        pool.set_effect(SetEffect {
+            effect_block_index,
+            type_specific_block_offset_instance_1: offset,
+            ..SetEffect::default()
+        })
+    }
+
+    fn set_constant_force(pool: &mut TestPool, offset: u16, magnitude: f32) -> Result<(), ()> {
+        let constant_force = SetConstantForce {
+            effect_block_index: 0,
+            magnitude,
+        };
+        pool.set_parameter(offset, 0, EffectParameter::ConstantForce(constant_force))
+    }
+
+    fn magnitude(pool: &TestPool, effect_block_index: u8) -> Option<f32> {
+        match pool.get_effect(effect_block_index)?.parameter_1? {
+            EffectParameter::ConstantForce(constant_force) => Some(constant_force.magnitude),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn effects_share_parameter_block() {
+        let mut pool = TestPool::new();
+        let offset = TestPool::parameter_block_offset(2);
+        set_effect(&mut pool, 1, offset).unwrap();
+        set_effect(&mut pool, 2, offset).unwrap();
+        set_effect(&mut pool, 3, TestPool::parameter_block_offset(1)).unwrap();
+
+        set_constant_force(&mut pool, offset, 0.5).unwrap();
+        assert_eq!(magnitude(&pool, 1), Some(0.5));
+        assert_eq!(magnitude(&pool, 2), Some(0.5));
+        assert_eq!(magnitude(&pool, 3), None);
+
+        // Writing the block again changes both
+        set_constant_force(&mut pool, offset, -0.25).unwrap();
+        assert_eq!(magnitude(&pool, 1), Some(-0.25));
+        assert_eq!(magnitude(&pool, 2), Some(-0.25));
+
+        // An effect set later uses the block that is already stored
+        set_effect(&mut pool, 4, offset).unwrap();
+        assert_eq!(magnitude(&pool, 4), Some(-0.25));
+    }
+
+    #[test]
+    fn set_effect_rejects_invalid_parameter_block_offsets() {
+        let mut pool = TestPool::new();
+        let first_block = TestPool::parameter_block_offset(0);
+
+        assert!(set_effect(&mut pool, 1, 0).is_ok());
+        assert!(set_effect(&mut pool, 1, first_block).is_ok());
+        assert!(set_effect(&mut pool, 1, first_block - 1).is_err());
+        assert!(set_effect(&mut pool, 1, first_block + 1).is_err());
+        assert!(set_effect(&mut pool, 1, TestPool::parameter_block_offset(3)).is_ok());
+        assert!(set_effect(&mut pool, 1, TestPool::parameter_block_offset(4)).is_err());
+    }
+
+    #[test]
+    fn parameter_block_is_written_before_effect() {
+        let mut pool = TestPool::new();
+        let offset = TestPool::parameter_block_offset(1);
+
+        // Hosts download the parameter blocks first, and then set the effect that uses them
+        set_constant_force(&mut pool, offset, 0.75).unwrap();
+        set_effect(&mut pool, 1, offset).unwrap();
+        assert_eq!(magnitude(&pool, 1), Some(0.75));
+        assert!(pool.render_state(1).is_ok());
+    }
+
+    #[test]
+    fn parameter_rejects_invalid_parameter_block_offsets() {
+        let mut pool = TestPool::new();
+        let first_block = TestPool::parameter_block_offset(0);
+
+        assert!(set_constant_force(&mut pool, 0, 1.0).is_err());
+        assert!(set_constant_force(&mut pool, first_block + 1, 1.0).is_err());
+        assert!(set_constant_force(&mut pool, TestPool::parameter_block_offset(4), 1.0).is_err());
+        assert!(set_constant_force(&mut pool, first_block, 1.0).is_ok());
+    }
+
+    #[test]
+    fn parameter_blocks_outlive_freed_effects() {
+        let mut pool = TestPool::new();
+        let offset = TestPool::parameter_block_offset(0);
+        set_constant_force(&mut pool, offset, 0.5).unwrap();
+        set_effect(&mut pool, 1, offset).unwrap();
+
+        // The block belongs to the host, an effect set after freeing the first one still uses it
+        pool.free_effect(1).unwrap();
+        set_effect(&mut pool, 2, offset).unwrap();
+        assert_eq!(magnitude(&pool, 2), Some(0.5));
+    }
+
+    #[test]
+    fn custom_data_offsets_are_relative_to_effect_region() {
+        let mut pool = TestPool::new();
+        assert_eq!(pool.new_effect(10), Some(1));
+        assert_eq!(pool.new_effect(10), Some(2));
+
+        pool.write_custom_data(2, 2, &[1, 2]).unwrap();
+        assert_eq!(&pool.custom_data(2)[..4], &[0, 0, 1, 2]);
+        assert!(pool.custom_data(1).iter().all(|&byte| byte == 0));
+        assert_eq!(
+            pool.available(),
+            2 * Report::<SetEffect>::RAM_SIZE + 4 * PARAMETER_BLOCK_SIZE + 80
+        );
+    }
+}
@@ -0,0 +1,23 @@
+use racing_wheel_core::hardware;
+use stm32f1xx_hal::{pac::TIM2, prelude::*, timer::CounterUs};
+
+// Update timer on TIM2
+pub struct Clock {
+    counter: CounterUs<TIM2>,
+}
+
+impl Clock {
+    pub fn new(counter: CounterUs<TIM2>) -> Self {
+        Self { counter }
+    }
+}
+
+impl hardware::Clock for Clock {
+    fn start(&mut self, period_ms: u32) {
+        self.counter.start(period_ms.millis()).unwrap();
+    }
+
+    fn wait(&mut self) -> bool {
+        self.counter.wait().is_ok()
+    }
+}
@@ -1,4 +1,5 @@
-use config::config::Config;
+use config::config::{Config, TORQUE_CURVE_SIZE};
+use racing_wheel_core::hardware::ConfigStorage;
 use stm32f1xx_hal::flash::{FlashWriter, FLASH_START};
 
 const CONFIG_PAGE_PADDING: usize = 1024 - ::core::mem::size_of::<Config>();
@@ -23,6 +24,32 @@ const CONFIG_PAGE: ConfigPage = ConfigPage {
         motor_deadband: 0.0001,
         motor_frequency_hz: 20_000,
         update_frequency_hz: 500,
+        acceleration_smoothing: 0.9,
+        low_pass_cutoff_hz: 0.0,
+        notch_frequency_hz: 0.0,
+        notch_q: 2.0,
+        slew_rate_limit: 0.0,
+        end_stop_stiffness: 10.0,
+        end_stop_damping: 0.0,
+        end_stop_start: 0.99,
+        end_stop_bump: 0.0,
+        torque_curve: [0.0; TORQUE_CURVE_SIZE],
+        torque_curve_points: 0,
+        constant_gain: 1.0,
+        ramp_gain: 1.0,
+        periodic_gain: 1.0,
+        condition_gain: 1.0,
+        custom_gain: 1.0,
+        reconstruction_latency_ms: 0,
+        idle_timeout_s: 0,
+        idle_fade_ms: 500,
+        idle_oscillation_damping: 0.0,
+        oscillation_window_ms: 1000,
+        oscillation_min_crossings: 4,
+        oscillation_min_velocity: 0.5,
+        oscillation_damping: 0.0,
+        oscillation_gain_reduction: 0.0,
+        oscillation_ramp_ms: 200,
     },
     _padding: [0; CONFIG_PAGE_PADDING],
 };
@@ -33,30 +60,39 @@ struct ConfigPage {
     _padding: [u8; CONFIG_PAGE_PADDING],
 }
 
-pub trait FlashMemoryData {
-    fn read_from_memory(flash_writer: &FlashWriter) -> Config;
-    fn write_to_memory(&self, flash_writer: &mut FlashWriter);
+// Stores the config in its flash page
+pub struct FlashConfigStorage<'a> {
+    flash_writer: FlashWriter<'a>,
 }
 
-impl FlashMemoryData for Config {
-    fn read_from_memory(flash_writer: &FlashWriter) -> Config {
+impl<'a> FlashConfigStorage<'a> {
+    pub fn new(flash_writer: FlashWriter<'a>) -> Self {
+        Self { flash_writer }
+    }
+}
+
+impl ConfigStorage for FlashConfigStorage<'_> {
+    fn read(&self) -> Config {
         let address = (&CONFIG_PAGE.config as *const Config) as u32 - FLASH_START;
-        let conf_bytes = flash_writer.read(address, size_of::<Config>()).unwrap();
+        let conf_bytes = self
+            .flash_writer
+            .read(address, size_of::<Config>())
+            .unwrap();
         let conf = unsafe { *(conf_bytes.as_ptr() as *const Config) };
 
         conf
     }
 
-    fn write_to_memory(&self, flash_writer: &mut FlashWriter) {
+    fn write(&mut self, config: &Config) {
         let config_bytes = unsafe {
             ::core::slice::from_raw_parts(
-                (self as *const Config) as *const u8,
+                (config as *const Config) as *const u8,
                 ::core::mem::size_of::<Config>(),
             )
         };
 
         let address = (&CONFIG_PAGE.config as *const Config) as u32 - FLASH_START;
-        let _ = flash_writer.page_erase(address);
-        let _ = flash_writer.write(address, config_bytes);
+        let _ = self.flash_writer.page_erase(address);
+        let _ = self.flash_writer.write(address, config_bytes);
     }
 }
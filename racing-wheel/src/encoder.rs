@@ -0,0 +1,32 @@
+use racing_wheel_core::hardware;
+use stm32f1xx_hal::pac::TIM4;
+
+const ENCODER_TO_DEG: f32 = 360.0 / 2400.0;
+
+// Rotary encoder counted by TIM4 in encoder mode, on PB6 and PB7
+pub struct Encoder {
+    timer: TIM4,
+}
+
+impl Encoder {
+    pub fn new(timer: TIM4) -> Self {
+        timer.smcr.write(|w| w.sms().encoder_mode_3());
+        timer.arr.write(|w| w.arr().variant(0xFF_FF));
+        timer.ccmr1_input().write(|w| w.cc1s().ti1());
+        timer.ccmr1_input().write(|w| w.cc2s().ti2());
+        timer.cr1.write(|w| w.cen().enabled());
+
+        Self { timer }
+    }
+}
+
+impl hardware::Encoder for Encoder {
+    fn angle(&mut self) -> f32 {
+        let steering_raw = self.timer.cnt.read().cnt().bits() as i16;
+        steering_raw as f32 * ENCODER_TO_DEG
+    }
+
+    fn reset(&mut self) {
+        self.timer.cnt.reset();
+    }
+}
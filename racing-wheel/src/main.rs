@@ -1,19 +1,22 @@
 #![no_std]
 #![no_main]
 
+mod clock;
 mod config;
-mod misc;
+mod encoder;
 mod motor;
-mod racing_wheel;
 mod simple_wheel;
 
-use crate::config::FlashMemoryData;
-use ::config::config::Config;
+use crate::config::FlashConfigStorage;
+use clock::Clock;
 use cortex_m::asm::delay;
 use cortex_m_rt::entry;
+use encoder::Encoder;
 use motor::Motor;
 use panic_halt as _;
-use racing_wheel::RacingWheel;
+use racing_wheel_core::{
+    controller::Controller, hardware::ConfigStorage, racing_wheel::RacingWheel,
+};
 use stm32f1xx_hal::flash::{FlashSize, SectorSize};
 use stm32f1xx_hal::gpio::*;
 use stm32f1xx_hal::pac::{Peripherals as HALPeripherals, SCB};
@@ -23,8 +26,6 @@ use stm32f1xx_hal::usb::{Peripheral, UsbBus};
 use usb_device::device::{UsbDeviceBuilder, UsbVidPid};
 use usb_hid_device::hid::HID;
 
-const ENCODER_TO_DEG: f32 = 360.0 / 2400.0;
-
 #[entry]
 fn main() -> ! {
     let dp = HALPeripherals::take().unwrap();
@@ -36,11 +37,7 @@ fn main() -> ! {
     gpiob.pb6.into_floating_input(&mut gpiob.crl);
     gpiob.pb7.into_floating_input(&mut gpiob.crl);
 
-    dp.TIM4.smcr.write(|w| w.sms().encoder_mode_3());
-    dp.TIM4.arr.write(|w| w.arr().variant(0xFF_FF));
-    dp.TIM4.ccmr1_input().write(|w| w.cc1s().ti1());
-    dp.TIM4.ccmr1_input().write(|w| w.cc2s().ti2());
-    dp.TIM4.cr1.write(|w| w.cen().enabled());
+    let encoder = Encoder::new(dp.TIM4);
 
     // Setup clocks
     let mut flash = dp.FLASH.constrain();
@@ -57,8 +54,8 @@ fn main() -> ! {
     assert!(clocks.usbclk_valid());
 
     // Setup config
-    let mut flash_writer = flash.writer(SectorSize::Sz1K, FlashSize::Sz128K);
-    let config = Config::read_from_memory(&flash_writer);
+    let config_storage = FlashConfigStorage::new(flash.writer(SectorSize::Sz1K, FlashSize::Sz128K));
+    let config = config_storage.read();
 
     // Setup motor
     let mut gpioa = dp.GPIOA.split();
@@ -76,7 +73,7 @@ fn main() -> ! {
         &clocks,
     );
     let (pwm_forward, pwm_reverse) = pwm.split();
-    let mut motor = Motor::new(motor_enable_pin.erase(), pwm_forward, pwm_reverse);
+    let motor = Motor::new(motor_enable_pin.erase(), pwm_forward, pwm_reverse);
 
     // Setup buttons
     let button_a = gpiob.pb10.into_pull_down_input(&mut gpiob.crh);
@@ -103,52 +100,25 @@ fn main() -> ! {
         .build();
 
     // Setup report timer
-    let mut report_timer = dp.TIM2.counter_us(&clocks);
-    let update_dt_ms = 1_000 / config.update_frequency_hz as u32;
-    report_timer.start(update_dt_ms.millis()).unwrap();
+    let report_timer = Clock::new(dp.TIM2.counter_us(&clocks));
+
+    let mut controller = Controller::new(encoder, motor, report_timer, config_storage, &config);
 
     // Main loop
     loop {
         // Poll usb
         usb_device.poll(&mut [&mut racing_wheel]);
 
-        // Handle events
-        if racing_wheel.get_device_mut().write_config_event() {
-            racing_wheel
-                .get_device()
-                .get_config()
-                .write_to_memory(&mut flash_writer);
-        }
-
         if racing_wheel.get_device_mut().reboot_device_event() {
             SCB::sys_reset()
         }
 
-        if racing_wheel.get_device_mut().reset_steering_event() {
-            dp.TIM4.cnt.reset();
-        }
-
-        // Update state
-        if report_timer.wait().is_ok() {
-            let steering_raw = dp.TIM4.cnt.read().cnt().bits() as i16;
-            let steering = steering_raw as f32 * ENCODER_TO_DEG;
-            let mut buttons = [false; 8];
-            buttons[0] = button_a.is_high();
-            buttons[1] = button_b.is_high();
-
-            racing_wheel.get_device_mut().set_steering(steering);
-            racing_wheel.get_device_mut().set_buttons(buttons);
-
-            racing_wheel.get_device_mut().advance(update_dt_ms);
-            let ffb = racing_wheel.get_device().get_force_feedback();
-
-            let config = racing_wheel.get_device().get_config();
-            motor.set_speed(
-                ffb,
-                (config.motor_min, config.motor_max),
-                config.motor_deadband,
-            );
+        let mut buttons = [false; 8];
+        buttons[0] = button_a.is_high();
+        buttons[1] = button_b.is_high();
 
+        // Handle events and update state
+        if controller.update(racing_wheel.get_device_mut(), buttons) {
             racing_wheel.send_input_reports();
         }
     }
@@ -1,4 +1,5 @@
 use cortex_m::prelude::*;
+use racing_wheel_core::hardware;
 use stm32f1xx_hal::gpio::*;
 
 pub struct Motor<PWMF, PWMR> {
@@ -19,12 +20,24 @@ impl<PWMF: _embedded_hal_PwmPin<Duty = u16>, PWMR: _embedded_hal_PwmPin<Duty = u
 
         motor.forward_pwm.enable();
         motor.reverse_pwm.enable();
-        motor.set_speed(0.0, (0.0, 0.0), 1.0);
+        hardware::Motor::set_speed(&mut motor, 0.0, (0.0, 0.0), 1.0);
 
         motor
     }
 
-    pub fn set_speed(&mut self, speed: f32, speed_range: (f32, f32), deadband: f32) {
+    fn get_max_duty(pwm: &impl _embedded_hal_PwmPin<Duty = u16>) -> f32 {
+        if pwm.get_max_duty() == 0 {
+            i16::MAX as f32
+        } else {
+            pwm.get_max_duty() as f32
+        }
+    }
+}
+
+impl<PWMF: _embedded_hal_PwmPin<Duty = u16>, PWMR: _embedded_hal_PwmPin<Duty = u16>> hardware::Motor
+    for Motor<PWMF, PWMR>
+{
+    fn set_speed(&mut self, speed: f32, speed_range: (f32, f32), deadband: f32) {
         let max_speed = f32::clamp(speed_range.1, 0.0, 1.0);
         let min_speed = f32::clamp(speed_range.0, 0.0, max_speed);
         let speed = f32::clamp(speed, -1.0, 1.0);
@@ -49,12 +62,4 @@ impl<PWMF: _embedded_hal_PwmPin<Duty = u16>, PWMR: _embedded_hal_PwmPin<Duty = u
             self.enable_pin.set_low();
         }
     }
-
-    fn get_max_duty(pwm: &impl _embedded_hal_PwmPin<Duty = u16>) -> f32 {
-        if pwm.get_max_duty() == 0 {
-            i16::MAX as f32
-        } else {
-            pwm.get_max_duty() as f32
-        }
-    }
 }
@@ -1,4 +1,4 @@
-use crate::misc::bitflags;
+use racing_wheel_core::misc::bitflags;
 use usb_device::{bus::UsbBus, prelude::UsbError};
 use usb_hid_device::{
     hid::ReportWriter,
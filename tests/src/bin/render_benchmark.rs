@@ -0,0 +1,202 @@
+// Compares the cost of the baseline `calculate_force_feedback`, which validates and renders the
+// stored effect every tick, with rendering it from the precomputed render state. Run with
+// `cargo run --release --bin render_benchmark`, and add `--features fixed-point` to measure the
+// fixed point calculations. The numbers are measured on the host and only compare the two paths,
+// the target needs a different number of cycles for the same code.
+#[cfg(not(feature = "fixed-point"))]
+use force_feedback::ffb::calculate_force_feedback;
+#[cfg(feature = "fixed-point")]
+use force_feedback::ffb_fixed::calculate_force_feedback;
+use force_feedback::{
+    effect::{Effect, EffectParameter},
+    force::{self, Force},
+    render_state::RenderState,
+    renderer::{EffectRenderer, SteeringState, TypedEffect},
+    reports::{
+        EffectType, SetCondition, SetConstantForce, SetCustomForce, SetEffect, SetEnvelope,
+        SetPeriodic, SetRampForce,
+    },
+};
+use std::hint::black_box;
+
+const ITERATIONS: u32 = 1_000_000;
+
+// A tick renders as many effects as the racing wheel plays at once
+const SIMULTANEOUS_EFFECTS: usize = 8;
+
+// Time stamp counter of the host where available, nanoseconds otherwise
+#[cfg(target_arch = "x86_64")]
+const UNIT: &str = "host cycles";
+#[cfg(not(target_arch = "x86_64"))]
+const UNIT: &str = "host ns";
+
+#[cfg(target_arch = "x86_64")]
+fn measure(mut f: impl FnMut(u32) -> f32) -> f64 {
+    // Safe, the time stamp counter is available on all x86_64 processors
+    let start = unsafe { core::arch::x86_64::_rdtsc() };
+    for time in 0..ITERATIONS {
+        black_box(f(black_box(time % 1000)));
+    }
+    let end = unsafe { core::arch::x86_64::_rdtsc() };
+    (end - start) as f64 / ITERATIONS as f64
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn measure(mut f: impl FnMut(u32) -> f32) -> f64 {
+    let start = std::time::Instant::now();
+    for time in 0..ITERATIONS {
+        black_box(f(black_box(time % 1000)));
+    }
+    start.elapsed().as_nanos() as f64 / ITERATIONS as f64
+}
+
+// Sweeps the steering axis so that condition effects are rendered in all their ranges
+fn steering_position(time: u32) -> f32 {
+    time as f32 / 1000.0 - 0.5
+}
+
+fn steering(time: u32) -> SteeringState {
+    let position = steering_position(time);
+    SteeringState::new(position, position, position)
+}
+
+fn effect(effect_type: EffectType, parameter_1: EffectParameter, envelope: bool) -> Effect {
+    Effect {
+        effect_report: Some(SetEffect {
+            effect_type,
+            duration: Some(1000),
+            sample_period: Some(10),
+            gain: 0.8,
+            direction_enable: true,
+            direction_instance_1: 64,
+            ..Default::default()
+        }),
+        parameter_1: Some(parameter_1),
+        parameter_2: envelope.then_some(EffectParameter::Envelope(SetEnvelope {
+            effect_block_index: 1,
+            attack_level: 0.2,
+            fade_level: 0.0,
+            attack_time: 200,
+            fade_time: 200,
+        })),
+    }
+}
+
+fn condition(effect_type: EffectType) -> Effect {
+    effect(
+        effect_type,
+        EffectParameter::Condition(SetCondition {
+            cp_offset: 0.1,
+            positive_coefficient: 0.8,
+            negative_coefficient: 0.8,
+            positive_saturation: 0.9,
+            negative_saturation: 0.9,
+            dead_band: 0.02,
+            ..Default::default()
+        }),
+        false,
+    )
+}
+
+fn main() {
+    let effects = [
+        (
+            "constant",
+            effect(
+                EffectType::ConstantForce,
+                EffectParameter::ConstantForce(SetConstantForce {
+                    effect_block_index: 1,
+                    magnitude: 0.5,
+                }),
+                true,
+            ),
+        ),
+        (
+            "ramp",
+            effect(
+                EffectType::Ramp,
+                EffectParameter::RampForce(SetRampForce {
+                    effect_block_index: 1,
+                    ramp_start: -0.5,
+                    ramp_end: 0.5,
+                }),
+                true,
+            ),
+        ),
+        (
+            "sine",
+            effect(
+                EffectType::Sine,
+                EffectParameter::Periodic(SetPeriodic {
+                    effect_block_index: 1,
+                    magnitude: 0.5,
+                    offset: 0.1,
+                    phase: 9_000,
+                    period: 100,
+                }),
+                true,
+            ),
+        ),
+        ("spring", condition(EffectType::Spring)),
+        ("damper", condition(EffectType::Damper)),
+        ("friction", condition(EffectType::Friction)),
+        (
+            "custom",
+            effect(
+                EffectType::CustomForceData,
+                EffectParameter::CustomForce(SetCustomForce {
+                    effect_block_index: 1,
+                    custom_force_data_offset: 0,
+                    sample_count: 4,
+                }),
+                true,
+            ),
+        ),
+    ];
+    let custom_data = [0, 0, 64, 0, 127, 0, 192, 0];
+
+    println!(
+        "{:<10} {:>18} {:>18}",
+        "effect",
+        format!("before {}", UNIT),
+        format!("after {}", UNIT)
+    );
+    for (name, effect) in effects.iter() {
+        let render_state = RenderState::new(TypedEffect::try_from(effect).unwrap());
+
+        let before = measure(|time| {
+            let position = steering_position(time);
+            calculate_force_feedback(
+                black_box(effect),
+                time,
+                position,
+                position,
+                position,
+                &custom_data,
+            )
+        });
+        let after = measure(|time| {
+            force::to_f32(black_box(&render_state).render(time, &steering(time), &custom_data))
+        });
+
+        println!("{:<10} {:>18.1} {:>18.1}", name, before, after);
+    }
+
+    let render_states: Vec<RenderState> = effects
+        .iter()
+        .cycle()
+        .take(SIMULTANEOUS_EFFECTS)
+        .map(|(_, effect)| RenderState::new(TypedEffect::try_from(effect).unwrap()))
+        .collect();
+    let tick = measure(|time| {
+        let steering = steering(time);
+        let forces = black_box(&render_states)
+            .iter()
+            .map(|render_state| render_state.render(time, &steering, &custom_data));
+        force::to_f32(forces.sum::<Force>())
+    });
+    println!(
+        "{} effects per tick: {:.1} {}",
+        SIMULTANEOUS_EFFECTS, tick, UNIT
+    );
+}
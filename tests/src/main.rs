@@ -82,7 +82,7 @@ fn main() {
         prev_vel = velocity;
         prev_acc = acceleration;
 
-        let ffb = calculate_force_feedback(&effect, time, position, velocity, acceleration);
+        let ffb = calculate_force_feedback(&effect, time, position, velocity, acceleration, &[]);
 
         print!(
             "\rX: |{}| dX: |{}| ddX: |{}| FFB: |{}|",